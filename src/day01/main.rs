@@ -1,10 +1,7 @@
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    str::FromStr,
-};
+use std::str::FromStr;
 
-const PATH: &str = "src/day01/input.txt";
+#[path = "../manifest.rs"]
+mod manifest;
 
 fn main() -> anyhow::Result<()> {
     let input = get_input()?;
@@ -15,13 +12,12 @@ fn main() -> anyhow::Result<()> {
 }
 
 fn get_input() -> anyhow::Result<Vec<u64>> {
-    let file = File::open(PATH)?;
-    let reader = BufReader::new(file);
-
-    Ok(reader
-        .lines()
-        .map(|line| u64::from_str(&line.unwrap()))
-        .collect::<Result<Vec<u64>, _>>()?)
+    manifest::open("day01", None, |data| {
+        Ok(data
+            .lines()
+            .map(u64::from_str)
+            .collect::<Result<Vec<u64>, _>>()?)
+    })
 }
 
 fn calc<const T: usize>(data: &Vec<u64>) -> u32 {
@@ -1,75 +1,401 @@
 use std::{
     convert::TryFrom,
+    fmt::Display,
     fs::File,
-    io::{BufRead, BufReader},
+    io::{BufWriter, Write},
     str::FromStr,
 };
 
+use anyhow::Context;
+use flags::take_flag;
+use planner::{plan_aim, plan_direct, plan_route, Target};
+use script::parse_script;
+
+#[path = "../flags.rs"]
+mod flags;
+#[path = "../manifest.rs"]
+mod manifest;
+mod planner;
+mod script;
+
 fn main() -> anyhow::Result<()> {
-    let input = get_input()?;
-    println!("Part 1 result: {}", part1(&input));
-    println!("Part 2 result: {}", part2(&input));
+    let mut args = std::env::args().skip(1).collect::<Vec<String>>();
+
+    if let Some(target) = take_flag(&mut args, "--plan")? {
+        let max_magnitude = take_flag(&mut args, "--max")?
+            .map(|max| u64::from_str(&max))
+            .transpose()?;
+        let name = args.first().context("Expected a model to plan with")?;
+
+        let plan = plan_route(
+            get_model(name)?.as_ref(),
+            parse_target(&target)?,
+            max_magnitude,
+        )?;
+        for command in plan {
+            println!("{}", command);
+        }
+
+        return Ok(());
+    }
+
+    let input = match take_flag(&mut args, "--script")? {
+        Some(path) => load_script(&path)?,
+        None => get_input()?,
+    };
+    let mut args = args.into_iter();
+
+    match args.next() {
+        Some(name) => {
+            let model = get_model(&name)?;
+            let trajectory = trace(model.as_ref(), &input)?;
+            println!("{} result: {}", name, trajectory.last().position_product()?);
+
+            if let Some(step) = trajectory.first_surface_breach() {
+                println!(
+                    "Warning: submarine rose above the surface at step {}",
+                    step + 1
+                );
+            }
+
+            if let Some(path) = args.next() {
+                trajectory.write_csv(File::create(&path)?)?;
+                println!(
+                    "Wrote {} trajectory steps to {}",
+                    trajectory.steps.len(),
+                    path
+                );
+            }
+        }
+        None => {
+            println!("Part 1 result: {}", part1(&input)?);
+            println!("Part 2 result: {}", part2(&input)?);
+        }
+    }
+
     Ok(())
 }
 
-fn part1(input: &Vec<Command>) -> u64 {
-    let mut depth = 0;
-    let mut horizontal = 0;
+fn part1(input: &[Command]) -> anyhow::Result<i64> {
+    navigate(&DirectModel, input)?.position_product()
+}
+
+fn part2(input: &[Command]) -> anyhow::Result<i64> {
+    navigate(&AimModel, input)?.position_product()
+}
+
+fn navigate(model: &dyn NavigationModel, input: &[Command]) -> anyhow::Result<Submarine> {
+    let mut submarine = Submarine::default();
+
+    for (i, command) in input.iter().enumerate() {
+        model
+            .apply(&mut submarine, command)
+            .with_context(|| format!("Failed to apply command {}: {}", i + 1, command))?;
+    }
+
+    Ok(submarine)
+}
+
+fn trace(model: &dyn NavigationModel, input: &[Command]) -> anyhow::Result<Trajectory> {
+    let mut submarine = Submarine::default();
+    let mut steps = Vec::with_capacity(input.len());
+
+    for (i, command) in input.iter().enumerate() {
+        model
+            .apply(&mut submarine, command)
+            .with_context(|| format!("Failed to apply command {}: {}", i + 1, command))?;
+        steps.push(submarine);
+    }
+
+    Ok(Trajectory { steps })
+}
+
+fn get_model(name: &str) -> anyhow::Result<Box<dyn NavigationModel>> {
+    match name {
+        "direct" => Ok(Box::new(DirectModel)),
+        "aim" => Ok(Box::new(AimModel)),
+        "3d" => Ok(Box::new(ThreeDimensionalModel)),
+        _ => Err(anyhow::Error::msg(format!(
+            "Unknown navigation model: {} (expected one of: direct, aim, 3d)",
+            name
+        ))),
+    }
+}
+
+fn parse_target(value: &str) -> anyhow::Result<Target> {
+    let (horizontal, depth) = value.split_once(',').with_context(|| {
+        format!(
+            "Expected a target of the form <horizontal>,<depth>: {}",
+            value
+        )
+    })?;
+
+    Ok(Target {
+        horizontal: i64::from_str(horizontal.trim())?,
+        depth: i64::from_str(depth.trim())?,
+    })
+}
+
+fn get_input() -> anyhow::Result<Vec<Command>> {
+    manifest::open("day02", None, |data| {
+        data.lines()
+            .map(Command::try_from)
+            .collect::<Result<Vec<Command>, _>>()
+    })
+}
+
+fn load_script(path: &str) -> anyhow::Result<Vec<Command>> {
+    let source = std::fs::read_to_string(path)?;
+    parse_script(&source).with_context(|| format!("Failed to parse script {}", path))
+}
+
+// Every axis is signed so that the submarine can rise above the surface or aim upwards, all arithmetic is checked.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Submarine {
+    pub horizontal: i64,
+    pub depth: i64,
+    pub aim: i64,
+    pub lateral: i64,
+    pub lateral_aim: i64,
+}
+
+impl Submarine {
+    pub fn position_product(&self) -> anyhow::Result<i64> {
+        checked_mul(self.horizontal, self.depth)
+    }
+}
+
+fn checked_add(a: i64, b: i64) -> anyhow::Result<i64> {
+    a.checked_add(b)
+        .ok_or_else(|| anyhow::Error::msg(format!("Overflow computing {} + {}", a, b)))
+}
+
+fn checked_sub(a: i64, b: i64) -> anyhow::Result<i64> {
+    a.checked_sub(b)
+        .ok_or_else(|| anyhow::Error::msg(format!("Overflow computing {} - {}", a, b)))
+}
+
+fn checked_mul(a: i64, b: i64) -> anyhow::Result<i64> {
+    a.checked_mul(b)
+        .ok_or_else(|| anyhow::Error::msg(format!("Overflow computing {} * {}", a, b)))
+}
+
+// The state of the submarine after each command was applied.
+struct Trajectory {
+    pub steps: Vec<Submarine>,
+}
+
+impl Trajectory {
+    pub fn last(&self) -> Submarine {
+        self.steps.last().copied().unwrap_or_default()
+    }
+
+    // Returns the index of the first step which left the submarine above the surface.
+    pub fn first_surface_breach(&self) -> Option<usize> {
+        self.steps.iter().position(|step| step.depth < 0)
+    }
+
+    pub fn write_csv<W: Write>(&self, writer: W) -> anyhow::Result<()> {
+        let mut writer = BufWriter::new(writer);
+        writeln!(writer, "step,horizontal,depth,aim,lateral,lateral_aim")?;
+
+        for (i, step) in self.steps.iter().enumerate() {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{}",
+                i + 1,
+                step.horizontal,
+                step.depth,
+                step.aim,
+                step.lateral,
+                step.lateral_aim
+            )?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+trait NavigationModel {
+    fn apply(&self, submarine: &mut Submarine, command: &Command) -> anyhow::Result<()>;
+
+    // Produces commands which take a submarine from the surface to the target.
+    fn plan(&self, _target: Target, _max_magnitude: Option<u64>) -> anyhow::Result<Vec<Command>> {
+        Err(anyhow::Error::msg(
+            "Route planning is not supported by this navigation model",
+        ))
+    }
+}
+
+// Commands move the submarine directly along each axis.
+struct DirectModel;
+
+impl NavigationModel for DirectModel {
+    fn apply(&self, submarine: &mut Submarine, command: &Command) -> anyhow::Result<()> {
+        let magnitude = command.signed_magnitude()?;
 
-    for command in input {
         match command.direction {
-            Direction::Forward => horizontal += command.magnitude,
-            Direction::Down => depth += command.magnitude,
-            Direction::Up => depth -= command.magnitude,
+            Direction::Forward => {
+                submarine.horizontal = checked_add(submarine.horizontal, magnitude)?
+            }
+            Direction::Down => submarine.depth = checked_add(submarine.depth, magnitude)?,
+            Direction::Up => submarine.depth = checked_sub(submarine.depth, magnitude)?,
+            Direction::Back => submarine.horizontal = checked_sub(submarine.horizontal, magnitude)?,
+            Direction::Surface => submarine.depth = 0,
+            Direction::Left | Direction::Right => return Err(unsupported(command, "direct")),
         }
+
+        Ok(())
     }
 
-    depth * horizontal
+    fn plan(&self, target: Target, max_magnitude: Option<u64>) -> anyhow::Result<Vec<Command>> {
+        Ok(plan_direct(target, max_magnitude))
+    }
 }
 
-fn part2(input: &Vec<Command>) -> u64 {
-    let mut depth = 0;
-    let mut horizontal = 0;
-    let mut aim = 0;
+// Up and down adjust the aim, forward moves along it.
+struct AimModel;
+
+impl NavigationModel for AimModel {
+    fn apply(&self, submarine: &mut Submarine, command: &Command) -> anyhow::Result<()> {
+        let magnitude = command.signed_magnitude()?;
 
-    for command in input {
         match command.direction {
             Direction::Forward => {
-                horizontal += command.magnitude;
-                depth += aim * command.magnitude
+                submarine.horizontal = checked_add(submarine.horizontal, magnitude)?;
+                submarine.depth =
+                    checked_add(submarine.depth, checked_mul(submarine.aim, magnitude)?)?;
             }
-            Direction::Down => aim += command.magnitude,
-            Direction::Up => aim -= command.magnitude,
+            Direction::Down => submarine.aim = checked_add(submarine.aim, magnitude)?,
+            Direction::Up => submarine.aim = checked_sub(submarine.aim, magnitude)?,
+            Direction::Back => {
+                submarine.horizontal = checked_sub(submarine.horizontal, magnitude)?;
+                submarine.depth =
+                    checked_sub(submarine.depth, checked_mul(submarine.aim, magnitude)?)?;
+            }
+            Direction::Surface => {
+                submarine.depth = 0;
+                submarine.aim = 0;
+            }
+            Direction::Left | Direction::Right => return Err(unsupported(command, "aim")),
         }
+
+        Ok(())
     }
 
-    depth * horizontal
+    fn plan(&self, target: Target, max_magnitude: Option<u64>) -> anyhow::Result<Vec<Command>> {
+        plan_aim(target, max_magnitude)
+    }
 }
 
-fn get_input() -> anyhow::Result<Vec<Command>> {
-    const PATH: &str = "src/day02/input.txt";
+// Extends the aim model with a lateral axis: left and right steer the lateral aim (negative is left), which
+// forward then applies in the same way as the vertical aim.
+struct ThreeDimensionalModel;
 
-    let file = File::open(PATH)?;
-    let reader = BufReader::new(file);
+impl NavigationModel for ThreeDimensionalModel {
+    fn apply(&self, submarine: &mut Submarine, command: &Command) -> anyhow::Result<()> {
+        let magnitude = command.signed_magnitude()?;
 
-    Ok(reader
-        .lines()
-        .map(|line| Command::try_from(line.unwrap().as_str()))
-        .collect::<Result<Vec<Command>, _>>()?)
+        match command.direction {
+            Direction::Forward => {
+                submarine.horizontal = checked_add(submarine.horizontal, magnitude)?;
+                submarine.depth =
+                    checked_add(submarine.depth, checked_mul(submarine.aim, magnitude)?)?;
+                submarine.lateral = checked_add(
+                    submarine.lateral,
+                    checked_mul(submarine.lateral_aim, magnitude)?,
+                )?;
+            }
+            Direction::Down => submarine.aim = checked_add(submarine.aim, magnitude)?,
+            Direction::Up => submarine.aim = checked_sub(submarine.aim, magnitude)?,
+            Direction::Back => {
+                submarine.horizontal = checked_sub(submarine.horizontal, magnitude)?;
+                submarine.depth =
+                    checked_sub(submarine.depth, checked_mul(submarine.aim, magnitude)?)?;
+                submarine.lateral = checked_sub(
+                    submarine.lateral,
+                    checked_mul(submarine.lateral_aim, magnitude)?,
+                )?;
+            }
+            Direction::Surface => {
+                submarine.depth = 0;
+                submarine.aim = 0;
+            }
+            Direction::Left => {
+                submarine.lateral_aim = checked_sub(submarine.lateral_aim, magnitude)?
+            }
+            Direction::Right => {
+                submarine.lateral_aim = checked_add(submarine.lateral_aim, magnitude)?
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn unsupported(command: &Command, model: &str) -> anyhow::Error {
+    anyhow::Error::msg(format!(
+        "Command '{}' is not supported by the {} navigation model",
+        command, model
+    ))
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct Command {
     pub direction: Direction,
     pub magnitude: u64,
 }
 
+impl Command {
+    pub fn signed_magnitude(&self) -> anyhow::Result<i64> {
+        i64::try_from(self.magnitude).with_context(|| format!("Magnitude too large: {}", self))
+    }
+}
+
+impl Display for Command {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.direction {
+            Direction::Surface => write!(f, "{}", self.direction),
+            _ => write!(f, "{} {}", self.direction, self.magnitude),
+        }
+    }
+}
+
 impl TryFrom<&str> for Command {
     type Error = anyhow::Error;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        let parts = value.split(" ").collect::<Vec<&str>>();
-        let direction = Direction::try_from(parts[0])?;
-        let magnitude = u64::from_str(parts[1])?;
+        let parts = value.split_whitespace().collect::<Vec<&str>>();
+        let direction = match parts.first() {
+            Some(name) => Direction::try_from(*name)?,
+            None => return Err(anyhow::Error::msg("Empty command")),
+        };
+
+        // Surfacing is absolute, so it's the only command without a magnitude.
+        let magnitude = match (direction, &parts[1..]) {
+            (Direction::Surface, []) => 0,
+            (Direction::Surface, _) => {
+                return Err(anyhow::Error::msg(format!(
+                    "Command takes no magnitude: {}",
+                    value
+                )))
+            }
+            (_, [magnitude]) => u64::from_str(magnitude)
+                .with_context(|| format!("Invalid magnitude: {}", magnitude))?,
+            (_, []) => {
+                return Err(anyhow::Error::msg(format!(
+                    "Missing magnitude for command: {}",
+                    value
+                )))
+            }
+            (_, _) => {
+                return Err(anyhow::Error::msg(format!(
+                    "Unexpected trailing input in command: {}",
+                    value
+                )))
+            }
+        };
 
         Ok(Command {
             direction,
@@ -78,10 +404,15 @@ impl TryFrom<&str> for Command {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Direction {
     Forward,
     Down,
     Up,
+    Left,
+    Right,
+    Back,
+    Surface,
 }
 
 impl TryFrom<&str> for Direction {
@@ -92,7 +423,116 @@ impl TryFrom<&str> for Direction {
             "forward" => Ok(Direction::Forward),
             "down" => Ok(Direction::Down),
             "up" => Ok(Direction::Up),
+            "left" => Ok(Direction::Left),
+            "right" => Ok(Direction::Right),
+            "back" => Ok(Direction::Back),
+            "surface" => Ok(Direction::Surface),
             _ => Err(anyhow::Error::msg(format!("Unknown command: {}", value))),
         }
     }
 }
+
+impl Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Direction::Forward => "forward",
+            Direction::Down => "down",
+            Direction::Up => "up",
+            Direction::Left => "left",
+            Direction::Right => "right",
+            Direction::Back => "back",
+            Direction::Surface => "surface",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(lines: &[&str]) -> Vec<Command> {
+        lines
+            .iter()
+            .map(|line| Command::try_from(*line).unwrap())
+            .collect()
+    }
+
+    const EXAMPLE: [&str; 6] = [
+        "forward 5",
+        "down 5",
+        "forward 8",
+        "up 3",
+        "down 8",
+        "forward 2",
+    ];
+
+    #[test]
+    pub fn test_builtin_models() {
+        let input = parse(&EXAMPLE);
+        assert_eq!(part1(&input).unwrap(), 150);
+        assert_eq!(part2(&input).unwrap(), 900);
+    }
+
+    #[test]
+    pub fn test_three_dimensional_model() {
+        let input = parse(&["right 2", "down 1", "forward 3", "left 5", "forward 1"]);
+        let submarine = navigate(&ThreeDimensionalModel, &input).unwrap();
+
+        assert_eq!(submarine.horizontal, 4);
+        assert_eq!(submarine.depth, 4);
+        assert_eq!(submarine.lateral, 3);
+    }
+
+    #[test]
+    pub fn test_rising_above_surface_is_signed() {
+        let input = parse(&["forward 2", "down 1", "up 3"]);
+        let trajectory = trace(&DirectModel, &input).unwrap();
+
+        assert_eq!(trajectory.last().depth, -2);
+        assert_eq!(trajectory.first_surface_breach(), Some(2));
+        assert_eq!(trajectory.last().position_product().unwrap(), -4);
+    }
+
+    #[test]
+    pub fn test_overflow_is_reported() {
+        let input = parse(&["down 9223372036854775807", "down 1"]);
+        assert!(navigate(&DirectModel, &input).is_err());
+        assert!(navigate(&DirectModel, &parse(&["down 9223372036854775808"])).is_err());
+    }
+
+    #[test]
+    pub fn test_trajectory_csv() {
+        let input = parse(&EXAMPLE[..3]);
+        let trajectory = trace(&AimModel, &input).unwrap();
+
+        let mut csv = Vec::new();
+        trajectory.write_csv(&mut csv).unwrap();
+
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "step,horizontal,depth,aim,lateral,lateral_aim\n\
+             1,5,0,0,0,0\n\
+             2,5,0,5,0,0\n\
+             3,13,40,5,0,0\n"
+        );
+    }
+
+    #[test]
+    pub fn test_back_and_surface() {
+        let input = parse(&["down 2", "forward 3", "back 1", "surface", "forward 1"]);
+        let submarine = navigate(&AimModel, &input).unwrap();
+
+        assert_eq!(submarine.horizontal, 3);
+        assert_eq!(submarine.depth, 0);
+        assert_eq!(submarine.aim, 0);
+    }
+
+    #[test]
+    pub fn test_lateral_commands_rejected_by_planar_models() {
+        let input = parse(&["left 1"]);
+        assert!(navigate(&DirectModel, &input).is_err());
+        assert!(navigate(&AimModel, &input).is_err());
+    }
+}
@@ -0,0 +1,316 @@
+use super::{navigate, Command, Direction, NavigationModel};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Target {
+    pub horizontal: i64,
+    pub depth: i64,
+}
+
+// Plans a route to the target with the given model, then replays it through the model to check it lands there.
+pub fn plan_route(
+    model: &dyn NavigationModel,
+    target: Target,
+    max_magnitude: Option<u64>,
+) -> anyhow::Result<Vec<Command>> {
+    if max_magnitude == Some(0) {
+        return Err(anyhow::Error::msg("Maximum magnitude must be at least 1"));
+    }
+
+    let plan = model.plan(target, max_magnitude)?;
+    let reached = navigate(model, &plan)?;
+
+    if reached.horizontal != target.horizontal || reached.depth != target.depth {
+        return Err(anyhow::Error::msg(format!(
+            "Planned route reaches ({}, {}) instead of ({}, {})",
+            reached.horizontal, reached.depth, target.horizontal, target.depth
+        )));
+    }
+
+    Ok(plan)
+}
+
+// One move per axis is always minimal, it only needs splitting if the magnitudes are capped.
+pub fn plan_direct(target: Target, max_magnitude: Option<u64>) -> Vec<Command> {
+    let mut plan = Vec::new();
+
+    let horizontal = if target.horizontal < 0 {
+        Direction::Back
+    } else {
+        Direction::Forward
+    };
+    push(
+        &mut plan,
+        horizontal,
+        target.horizontal.unsigned_abs(),
+        max_magnitude,
+    );
+
+    let vertical = if target.depth < 0 {
+        Direction::Up
+    } else {
+        Direction::Down
+    };
+    push(
+        &mut plan,
+        vertical,
+        target.depth.unsigned_abs(),
+        max_magnitude,
+    );
+
+    plan
+}
+
+// Depth only changes when moving along a non-zero aim, so the shortest plans are:
+//   - `forward h` when no depth is needed,
+//   - `down d/h, forward h` when the horizontal distance divides the depth,
+//   - `forward h-1, down d, forward 1` otherwise.
+// That last plan needs a huge aim for deep targets, which splits badly when magnitudes are capped. So we also
+// consider diving to the nearest multiple first (`down q, forward h-r, down 1, forward r` where d = qh + r),
+// and covering the horizontal distance at zero aim before diving on a stretch of our own choosing (see `dive`),
+// keeping whichever candidate is shortest once split. Without a cap the result is minimal. With one, the
+// candidates balance their moves against the aim they need, which keeps plans short but doesn't guarantee
+// the shortest.
+pub fn plan_aim(target: Target, max_magnitude: Option<u64>) -> anyhow::Result<Vec<Command>> {
+    // Moving backwards along an aim negates the depth change, so plan the mirrored route and flip the moves.
+    let (forward, depth) = if target.horizontal < 0 {
+        (Direction::Back, -(target.depth as i128))
+    } else {
+        (Direction::Forward, target.depth as i128)
+    };
+    let (down, up) = if depth < 0 {
+        (Direction::Up, Direction::Down)
+    } else {
+        (Direction::Down, Direction::Up)
+    };
+    let h = target.horizontal.unsigned_abs();
+    let d = depth.unsigned_abs() as u64;
+
+    let mut candidates = Vec::new();
+
+    if d == 0 {
+        let mut plan = Vec::new();
+        push(&mut plan, forward, h, max_magnitude);
+        candidates.push(plan);
+    } else if let Some(q) = d.checked_div(h) {
+        let r = d % h;
+
+        if r == 0 {
+            let mut plan = Vec::new();
+            push(&mut plan, down, q, max_magnitude);
+            push(&mut plan, forward, h, max_magnitude);
+            candidates.push(plan);
+        } else {
+            let mut plan = Vec::new();
+            push(&mut plan, forward, h - 1, max_magnitude);
+            push(&mut plan, down, d, max_magnitude);
+            push(&mut plan, forward, 1, max_magnitude);
+            candidates.push(plan);
+
+            let mut plan = Vec::new();
+            push(&mut plan, down, q, max_magnitude);
+            push(&mut plan, forward, h - r, max_magnitude);
+            push(&mut plan, down, 1, max_magnitude);
+            push(&mut plan, forward, r, max_magnitude);
+            candidates.push(plan);
+        }
+
+        let mut plan = Vec::new();
+        push(&mut plan, forward, h, max_magnitude);
+        dive(&mut plan, forward, up, d, max_magnitude);
+        candidates.push(plan);
+    } else {
+        let mut plan = Vec::new();
+        dive(&mut plan, forward, up, d, max_magnitude);
+        candidates.push(plan);
+    }
+
+    candidates
+        .into_iter()
+        .min_by_key(|plan| plan.len())
+        .ok_or_else(|| anyhow::Error::msg("No route found"))
+}
+
+// Gains depth without moving overall: out a distance at zero aim, then back along the opposite aim. With
+// d = qa + r that's `forward a, up q, back a-r, up 1, back r`, where the stretch a is ours to choose. Without a
+// cap, a = 1 takes three moves. With one, short stretches need a lot of aim and long ones a lot of moving, so
+// we try stretches around the balance between them.
+fn dive(
+    plan: &mut Vec<Command>,
+    forward: Direction,
+    up: Direction,
+    d: u64,
+    max_magnitude: Option<u64>,
+) {
+    let back = if forward == Direction::Forward {
+        Direction::Back
+    } else {
+        Direction::Forward
+    };
+
+    let mut stretches = vec![1];
+    if let Some(max) = max_magnitude {
+        let max = max.max(1);
+        let balanced = (d / 2).isqrt().max(1);
+        for k in (balanced / max).saturating_sub(1)..=balanced / max + 2 {
+            stretches.push(k.saturating_mul(max));
+        }
+        stretches.extend(balanced.saturating_sub(1)..=balanced.saturating_add(1));
+
+        // Or the aim in whole moves, taking the stretch that makes it so.
+        let aim = (2 * (d as u128)).isqrt() as u64;
+        for k in (aim / max).saturating_sub(1)..=aim / max + 2 {
+            if let Some(stretch) = d.checked_div(k.saturating_mul(max)) {
+                stretches.extend([stretch, stretch.saturating_add(1)]);
+            }
+        }
+    }
+
+    let length = |a: u64| {
+        let (q, r) = (d / a, d % a);
+        let mut length = commands(a, max_magnitude) + commands(q, max_magnitude);
+        length += commands(a - r, max_magnitude);
+        if r > 0 {
+            length += 1 + commands(r, max_magnitude);
+        }
+        length
+    };
+    let a = stretches
+        .into_iter()
+        .filter(|a| (1..=d).contains(a))
+        .min_by_key(|a| (length(*a), *a))
+        .unwrap_or(1);
+
+    let (q, r) = (d / a, d % a);
+    push(plan, forward, a, max_magnitude);
+    push(plan, up, q, max_magnitude);
+    push(plan, back, a - r, max_magnitude);
+    if r > 0 {
+        push(plan, up, 1, max_magnitude);
+        push(plan, back, r, max_magnitude);
+    }
+}
+
+// How many commands `push` splits a move into.
+fn commands(magnitude: u64, max_magnitude: Option<u64>) -> u64 {
+    match max_magnitude {
+        Some(max) => magnitude.div_ceil(max.max(1)),
+        None => (magnitude > 0) as u64,
+    }
+}
+
+// Pushes a move of the given magnitude, split into as few commands as the cap allows.
+fn push(plan: &mut Vec<Command>, direction: Direction, magnitude: u64, max_magnitude: Option<u64>) {
+    let step = max_magnitude.unwrap_or(magnitude).max(1);
+    let mut remaining = magnitude;
+
+    while remaining > 0 {
+        let magnitude = remaining.min(step);
+        plan.push(Command {
+            direction,
+            magnitude,
+        });
+        remaining -= magnitude;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{AimModel, DirectModel};
+    use super::*;
+
+    #[test]
+    pub fn test_direct_routes() {
+        let target = Target {
+            horizontal: -7,
+            depth: 10,
+        };
+
+        assert_eq!(plan_route(&DirectModel, target, None).unwrap().len(), 2);
+        assert_eq!(plan_route(&DirectModel, target, Some(3)).unwrap().len(), 7);
+    }
+
+    #[test]
+    pub fn test_aim_routes_are_minimal_without_cap() {
+        let cases = vec![
+            ((0, 0), 0),
+            ((5, 0), 1),
+            ((5, 15), 2),
+            ((5, -15), 2),
+            ((5, 17), 3),
+            ((-5, 17), 3),
+            ((0, 4), 3),
+        ];
+
+        for ((horizontal, depth), expected) in cases {
+            let target = Target { horizontal, depth };
+            let plan = plan_route(&AimModel, target, None).unwrap();
+            assert_eq!(plan.len(), expected, "{:?}", target);
+        }
+    }
+
+    #[test]
+    pub fn test_aim_routes_with_cap() {
+        let target = Target {
+            horizontal: 1000,
+            depth: 1_000_123,
+        };
+
+        // Dive to 1000 first, then a single extra unit of aim for the last 123 units.
+        let plan = plan_route(&AimModel, target, Some(100)).unwrap();
+        assert_eq!(plan.len(), 10 + 9 + 1 + 2);
+
+        // Going out and back 10 needs an aim of only 10, rather than going out 1 with an aim of 100.
+        let target = Target {
+            horizontal: 0,
+            depth: 100,
+        };
+        let plan = plan_route(&AimModel, target, Some(10)).unwrap();
+        assert_eq!(
+            plan,
+            vec![
+                Command {
+                    direction: Direction::Forward,
+                    magnitude: 10,
+                },
+                Command {
+                    direction: Direction::Up,
+                    magnitude: 10,
+                },
+                Command {
+                    direction: Direction::Back,
+                    magnitude: 10,
+                },
+            ]
+        );
+
+        // Likewise when the horizontal distance is too short to dive along.
+        let target = Target {
+            horizontal: -1,
+            depth: -1000,
+        };
+        assert_eq!(
+            plan_route(&AimModel, target, Some(10)).unwrap().len(),
+            1 + 2 + 5 + 2
+        );
+
+        // A prime depth can't be split evenly, so it takes the extra unit of aim.
+        let target = Target {
+            horizontal: 0,
+            depth: 101,
+        };
+        assert_eq!(plan_route(&AimModel, target, Some(10)).unwrap().len(), 5);
+    }
+
+    #[test]
+    pub fn test_capped_aim_routes_land() {
+        for horizontal in -12..=12 {
+            for depth in -150..=150 {
+                let target = Target { horizontal, depth };
+                for max in [1, 3, 10] {
+                    let capped = plan_route(&AimModel, target, Some(max)).unwrap();
+                    assert!(capped.iter().all(|command| command.magnitude <= max));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use std::{collections::HashMap, convert::TryFrom, fmt::Display, str::FromStr};
+
+use super::{Command, Direction};
+
+// Guards against scripts like `repeat 1000000000 { ... }` exhausting memory when expanded.
+const MAX_COMMANDS: usize = 10_000_000;
+
+// Parses a mission script into the flat list of commands it expands to.
+//
+// On top of the plain `<direction> <magnitude>` lines a script supports:
+//   - comments starting with `#` and blank lines,
+//   - `repeat N {` ... `}` blocks, which may be nested,
+//   - `macro name {` ... `}` definitions, invoked by writing `name` on its own line once defined.
+pub fn parse_script(source: &str) -> Result<Vec<Command>, ParseError> {
+    let mut parser = Parser {
+        lines: source
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, strip_comment(line).trim()))
+            .collect(),
+        position: 0,
+        macros: HashMap::new(),
+    };
+
+    parser.parse_block(None)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    fn new(line: usize, message: impl Into<String>) -> Self {
+        ParseError {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+struct Parser<'a> {
+    lines: Vec<(usize, &'a str)>,
+    position: usize,
+    macros: HashMap<String, Vec<Command>>,
+}
+
+impl<'a> Parser<'a> {
+    // Parses lines until the end of the script, or the closing brace of the block opened on `opened_at`.
+    fn parse_block(&mut self, opened_at: Option<usize>) -> Result<Vec<Command>, ParseError> {
+        let mut commands = Vec::new();
+
+        while let Some(&(line, content)) = self.lines.get(self.position) {
+            self.position += 1;
+
+            let tokens = content.split_whitespace().collect::<Vec<&str>>();
+
+            match tokens.as_slice() {
+                [] => {}
+                ["}"] => {
+                    return match opened_at {
+                        Some(_) => Ok(commands),
+                        None => Err(ParseError::new(line, "Unexpected '}'")),
+                    }
+                }
+                ["repeat", count, "{"] => {
+                    let count = usize::from_str(count).map_err(|_| {
+                        ParseError::new(line, format!("Invalid repeat count: {}", count))
+                    })?;
+                    let body = self.parse_block(Some(line))?;
+
+                    let total = body
+                        .len()
+                        .checked_mul(count)
+                        .and_then(|n| n.checked_add(commands.len()))
+                        .filter(|n| *n <= MAX_COMMANDS)
+                        .ok_or_else(|| {
+                            ParseError::new(
+                                line,
+                                format!("Script expands to more than {} commands", MAX_COMMANDS),
+                            )
+                        })?;
+
+                    commands.reserve(total - commands.len());
+                    for _ in 0..count {
+                        commands.extend_from_slice(&body);
+                    }
+                }
+                ["repeat", ..] => {
+                    return Err(ParseError::new(line, "Expected 'repeat <count> {'"));
+                }
+                ["macro", name, "{"] => {
+                    if Direction::try_from(*name).is_ok()
+                        || ["repeat", "macro"].contains(name)
+                        || self.macros.contains_key(*name)
+                    {
+                        return Err(ParseError::new(
+                            line,
+                            format!("Macro name is already in use: {}", name),
+                        ));
+                    }
+
+                    let body = self.parse_block(Some(line))?;
+                    self.macros.insert(name.to_string(), body);
+                }
+                ["macro", ..] => {
+                    return Err(ParseError::new(line, "Expected 'macro <name> {'"));
+                }
+                [name] if self.macros.contains_key(*name) => {
+                    let body = &self.macros[*name];
+                    if commands.len() + body.len() > MAX_COMMANDS {
+                        return Err(ParseError::new(
+                            line,
+                            format!("Script expands to more than {} commands", MAX_COMMANDS),
+                        ));
+                    }
+
+                    commands.extend_from_slice(body);
+                }
+                _ => {
+                    let command = Command::try_from(content)
+                        .map_err(|e| ParseError::new(line, e.to_string()))?;
+                    commands.push(command);
+                }
+            }
+        }
+
+        match opened_at {
+            Some(line) => Err(ParseError::new(line, "Block is never closed")),
+            None => Ok(commands),
+        }
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    match line.find('#') {
+        Some(idx) => &line[..idx],
+        None => line,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(commands: &[Command]) -> Vec<String> {
+        commands.iter().map(|c| c.to_string()).collect()
+    }
+
+    #[test]
+    pub fn test_plain_input_is_a_script() {
+        let commands = parse_script("forward 5\ndown  5\n\nup 3").unwrap();
+        assert_eq!(render(&commands), vec!["forward 5", "down 5", "up 3"]);
+    }
+
+    #[test]
+    pub fn test_repeats_and_macros() {
+        const SCRIPT: &str = "
+            # Dive, then sweep along the bottom.
+            macro dive {
+                down 2
+                forward 1  # trailing comments are fine too
+            }
+
+            repeat 2 {
+                dive
+                repeat 2 {
+                    back 1
+                }
+            }
+            surface
+        ";
+
+        let commands = parse_script(SCRIPT).unwrap();
+        assert_eq!(
+            render(&commands),
+            vec![
+                "down 2",
+                "forward 1",
+                "back 1",
+                "back 1",
+                "down 2",
+                "forward 1",
+                "back 1",
+                "back 1",
+                "surface"
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_errors_report_line_numbers() {
+        let cases = vec![
+            ("forward 1\nsideways 2", 2),
+            ("forward\n", 1),
+            ("repeat 2 {\nforward 1\n", 1),
+            ("forward 1\n}\n", 2),
+            ("repeat x {\n}", 1),
+            ("macro up {\n}", 1),
+            ("dive\nmacro dive {\n}", 1),
+            ("repeat 100000 {\nrepeat 100000 {\nforward 1\n}\n}", 1),
+        ];
+
+        for (script, line) in cases {
+            let err = parse_script(script).unwrap_err();
+            assert_eq!(err.line, line, "{}: {}", script, err);
+        }
+    }
+}
@@ -1,110 +1,522 @@
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-};
+use std::{convert::TryFrom, fmt::Display};
+
+use anyhow::Context;
+
+#[path = "../manifest.rs"]
+mod manifest;
+#[cfg(test)]
+#[path = "../rng.rs"]
+mod rng;
 
 fn main() -> anyhow::Result<()> {
     let input = get_input()?;
+
+    if std::env::args().any(|arg| arg == "--report") {
+        println!("{}", Breakdown::new(&input));
+    }
+
     println!("Part 1 result: {}", part1(&input)?);
     println!("Part 2 result: {}", part2(&input)?);
     Ok(())
 }
 
-fn get_input() -> anyhow::Result<Vec<String>> {
-    const PATH: &str = "src/day03/input.txt";
+fn get_input() -> anyhow::Result<Report> {
+    manifest::open("day03", None, |data| {
+        let lines = data.lines().map(str::to_string).collect::<Vec<String>>();
+        Report::try_from(lines.as_slice())
+    })
+}
 
-    let file = File::open(PATH)?;
-    let reader = BufReader::new(file);
+fn part1(report: &Report) -> anyhow::Result<u128> {
+    let (gamma, epsilon) = gamma_epsilon(report, &report.count_bits());
 
-    Ok(reader
-        .lines()
-        .map(|line| line.unwrap().clone())
-        .collect::<Vec<String>>())
+    gamma
+        .checked_mul(epsilon)
+        .context("Power consumption overflows a u128")
 }
 
-fn part1(data: &Vec<String>) -> anyhow::Result<u32> {
-    let counts = count_bits(data);
+fn gamma_epsilon(report: &Report, counts: &[[usize; 2]]) -> (u128, u128) {
+    let gamma = counts
+        .iter()
+        .fold(0, |acc, count| (acc << 1) | (count[1] >= count[0]) as u128);
 
-    let gamma = u32::from_str_radix(
-        &counts
-            .iter()
-            .map(|count| if count[0] > count[1] { '0' } else { '1' })
-            .collect::<String>(),
-        2,
-    )?;
+    (gamma, !gamma & report.mask())
+}
 
-    let epsilon = u32::from_str_radix(
-        &counts
-            .iter()
-            .map(|count| if count[0] > count[1] { '1' } else { '0' })
-            .collect::<String>(),
-        2,
+fn part2(report: &Report) -> anyhow::Result<u128> {
+    let [ox_rating, co2_rating] = sorted_ratings(
+        report,
+        [
+            RatingCriteria::OXYGEN_GENERATOR,
+            RatingCriteria::CO2_SCRUBBER,
+        ],
     )?;
 
-    Ok(gamma * epsilon)
+    co2_rating
+        .checked_mul(ox_rating)
+        .context("Life support rating overflows a u128")
 }
 
-fn part2(data: &Vec<String>) -> anyhow::Result<u32> {
-    let max_bits = data[0].len();
+// Computes several ratings from one sorted copy of the readings. Readings sharing a prefix form a contiguous
+// range once sorted, and within it the readings with a 0 in the next column come before those with a 1. So each
+// filtering step is a binary search which narrows the range, rather than a recount of every candidate.
+fn sorted_ratings<const N: usize>(
+    report: &Report,
+    criteria: [RatingCriteria; N],
+) -> Result<[u128; N], RatingError> {
+    let mut sorted = report.readings.clone();
+    sorted.sort_unstable();
+
+    let mut ranges = [(0, sorted.len()); N];
+
+    for column in 0..report.width {
+        for (range, criteria) in ranges.iter_mut().zip(criteria.iter()) {
+            let (start, end) = *range;
+            if end - start <= 1 {
+                continue;
+            }
 
-    let mut ox_candidates = data.clone();
-    let mut bit = 0;
+            let split = start
+                + sorted[start..end].partition_point(|reading| report.bit(*reading, column) == 0);
+            let desired_bit = criteria.desired_bit([split - start, end - split]);
 
-    while ox_candidates.len() > 1 && bit < max_bits {
-        let counts = count_bits(&ox_candidates);
+            *range = if desired_bit == 0 {
+                (start, split)
+            } else {
+                (split, end)
+            };
 
-        let desired_bit = if counts[bit][0] > counts[bit][1] {
-            '0'
-        } else {
-            '1'
+            if range.0 == range.1 {
+                return Err(RatingError::NoCandidates { column });
+            }
+        }
+    }
+
+    let mut ratings = [0; N];
+    for (rating, (start, end)) in ratings.iter_mut().zip(ranges.iter()) {
+        *rating = match end - start {
+            0 => return Err(RatingError::NoCandidates { column: 0 }),
+            1 => sorted[*start],
+            remaining => return Err(RatingError::Ambiguous { remaining }),
         };
+    }
+
+    Ok(ratings)
+}
+
+// Filters the readings column by column, keeping those whose bit matches the one chosen by the criteria, until
+// a single reading remains. This is the straightforward version of `sorted_ratings`, kept to check it against.
+#[cfg(test)]
+fn rating(report: &Report, criteria: RatingCriteria) -> Result<u128, RatingError> {
+    let mut candidates = report.readings.clone();
+    let mut column = 0;
 
-        ox_candidates = ox_candidates
-            .into_iter()
-            .filter(|num| num.chars().nth(bit).unwrap() == desired_bit)
-            .collect();
+    while candidates.len() > 1 && column < report.width {
+        let counts = count_column(&candidates, report.shift(column));
+        let desired_bit = criteria.desired_bit(counts);
 
-        bit += 1;
+        candidates.retain(|reading| report.bit(*reading, column) == desired_bit);
+
+        if candidates.is_empty() {
+            return Err(RatingError::NoCandidates { column });
+        }
+
+        column += 1;
     }
 
-    let ox_rating = u32::from_str_radix(&ox_candidates[0], 2)?;
+    match candidates.as_slice() {
+        [] => Err(RatingError::NoCandidates { column: 0 }),
+        [rating] => Ok(*rating),
+        _ => Err(RatingError::Ambiguous {
+            remaining: candidates.len(),
+        }),
+    }
+}
 
-    let mut co2_candidates = data.clone();
-    let mut bit = 0;
-    while co2_candidates.len() > 1 && bit < max_bits {
-        let counts = count_bits(&co2_candidates);
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BitCriterion {
+    MostCommon,
+    LeastCommon,
+}
 
-        let desired_bit = if counts[bit][0] > counts[bit][1] {
-            '1'
-        } else {
-            '0'
-        };
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RatingCriteria {
+    pub criterion: BitCriterion,
+    // The bit to keep when a column has as many zeros as ones.
+    pub tie_break: u128,
+}
+
+impl RatingCriteria {
+    pub const OXYGEN_GENERATOR: RatingCriteria = RatingCriteria {
+        criterion: BitCriterion::MostCommon,
+        tie_break: 1,
+    };
 
-        co2_candidates = co2_candidates
-            .into_iter()
-            .filter(|num| num.chars().nth(bit).unwrap() == desired_bit)
-            .collect();
+    pub const CO2_SCRUBBER: RatingCriteria = RatingCriteria {
+        criterion: BitCriterion::LeastCommon,
+        tie_break: 0,
+    };
 
-        bit += 1;
+    pub fn desired_bit(&self, counts: [usize; 2]) -> u128 {
+        if counts[0] == counts[1] {
+            return self.tie_break;
+        }
+
+        let most_common = (counts[1] > counts[0]) as u128;
+        match self.criterion {
+            BitCriterion::MostCommon => most_common,
+            BitCriterion::LeastCommon => most_common ^ 1,
+        }
     }
+}
 
-    let co2_rating = u32::from_str_radix(&co2_candidates[0], 2)?;
+#[derive(Debug, PartialEq, Eq)]
+enum RatingError {
+    // Every candidate was filtered out at the given column.
+    NoCandidates { column: usize },
+    // Several identical readings survived every column.
+    Ambiguous { remaining: usize },
+}
 
-    Ok(co2_rating * ox_rating)
+impl Display for RatingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RatingError::NoCandidates { column } => {
+                write!(f, "No candidates remain after filtering column {}", column)
+            }
+            RatingError::Ambiguous { remaining } => write!(
+                f,
+                "{} candidates remain after filtering every column",
+                remaining
+            ),
+        }
+    }
 }
 
-fn count_bits(data: &Vec<String>) -> Vec<[u32; 2]> {
-    let mut counts = data[0].chars().map(|_| [0, 0]).collect::<Vec<[u32; 2]>>();
+impl std::error::Error for RatingError {}
 
-    for datum in data {
-        for (i, c) in datum.chars().enumerate() {
-            if c == '0' {
-                counts[i][0] += 1;
-            } else {
-                counts[i][1] += 1;
+// Everything derived from the report, kept around to debug which column led to an unexpected rating.
+struct Breakdown {
+    pub width: usize,
+    pub columns: Vec<ColumnSummary>,
+    pub gamma: u128,
+    pub epsilon: u128,
+    pub oxygen: Result<u128, RatingError>,
+    pub co2: Result<u128, RatingError>,
+}
+
+struct ColumnSummary {
+    pub zeros: usize,
+    pub ones: usize,
+}
+
+impl ColumnSummary {
+    // None when the column is tied.
+    pub fn majority(&self) -> Option<u128> {
+        match self.zeros.cmp(&self.ones) {
+            std::cmp::Ordering::Less => Some(1),
+            std::cmp::Ordering::Equal => None,
+            std::cmp::Ordering::Greater => Some(0),
+        }
+    }
+
+    // 1.0 for a column with as many zeros as ones, down to 0.0 when every reading has the same bit.
+    pub fn balance(&self) -> f64 {
+        let total = self.zeros + self.ones;
+        if total == 0 {
+            return 1.0;
+        }
+
+        1.0 - (self.zeros as f64 - self.ones as f64).abs() / total as f64
+    }
+}
+
+impl Breakdown {
+    pub fn new(report: &Report) -> Self {
+        let counts = report.count_bits();
+        let (gamma, epsilon) = gamma_epsilon(report, &counts);
+
+        // Computed separately so that one rating failing doesn't hide the other.
+        let oxygen = sorted_ratings(report, [RatingCriteria::OXYGEN_GENERATOR]).map(|[r]| r);
+        let co2 = sorted_ratings(report, [RatingCriteria::CO2_SCRUBBER]).map(|[r]| r);
+
+        Breakdown {
+            width: report.width,
+            columns: counts
+                .iter()
+                .map(|count| ColumnSummary {
+                    zeros: count[0],
+                    ones: count[1],
+                })
+                .collect(),
+            gamma,
+            epsilon,
+            oxygen,
+            co2,
+        }
+    }
+
+    fn write_value(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        name: &str,
+        value: &Result<u128, RatingError>,
+    ) -> std::fmt::Result {
+        match value {
+            Ok(value) => writeln!(
+                f,
+                "{:<8} {:0width$b} ({})",
+                name,
+                value,
+                value,
+                width = self.width
+            ),
+            Err(e) => writeln!(f, "{:<8} error: {}", name, e),
+        }
+    }
+}
+
+impl Display for Breakdown {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "column    zeros     ones  majority  balance")?;
+
+        for (i, column) in self.columns.iter().enumerate() {
+            let majority = match column.majority() {
+                Some(bit) => bit.to_string(),
+                None => "tie".to_string(),
+            };
+
+            writeln!(
+                f,
+                "{:>6} {:>8} {:>8} {:>9} {:>8.3}",
+                i,
+                column.zeros,
+                column.ones,
+                majority,
+                column.balance()
+            )?;
+        }
+
+        writeln!(f)?;
+        self.write_value(f, "gamma", &Ok(self.gamma))?;
+        self.write_value(f, "epsilon", &Ok(self.epsilon))?;
+        self.write_value(f, "oxygen", &self.oxygen)?;
+        self.write_value(f, "co2", &self.co2)
+    }
+}
+
+// Readings are packed into the low `width` bits of a u128, so column 0 (the leftmost digit) is the most
+// significant bit.
+struct Report {
+    pub width: usize,
+    pub readings: Vec<u128>,
+}
+
+impl Report {
+    pub fn mask(&self) -> u128 {
+        u128::MAX >> (128 - self.width)
+    }
+
+    pub fn shift(&self, column: usize) -> usize {
+        self.width - 1 - column
+    }
+
+    pub fn bit(&self, reading: u128, column: usize) -> u128 {
+        (reading >> self.shift(column)) & 1
+    }
+
+    // Returns the `[zeros, ones]` counts of each column, leftmost column first.
+    pub fn count_bits(&self) -> Vec<[usize; 2]> {
+        let mut ones = vec![0; self.width];
+
+        // Only visit the set bits of each reading, clearing the lowest one each time.
+        for reading in &self.readings {
+            let mut bits = *reading;
+            while bits != 0 {
+                ones[bits.trailing_zeros() as usize] += 1;
+                bits &= bits - 1;
             }
         }
+
+        ones.iter()
+            .rev()
+            .map(|ones| [self.readings.len() - ones, *ones])
+            .collect()
     }
+}
+
+impl TryFrom<&[String]> for Report {
+    type Error = anyhow::Error;
+
+    fn try_from(lines: &[String]) -> Result<Self, Self::Error> {
+        let width = lines.first().context("Diagnostic report is empty")?.len();
+
+        if width == 0 || width > 128 {
+            return Err(anyhow::Error::msg(format!(
+                "Readings must be between 1 and 128 bits wide, found {}",
+                width
+            )));
+        }
+
+        let readings = lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                if line.len() != width || !line.chars().all(|c| c == '0' || c == '1') {
+                    return Err(anyhow::Error::msg(format!(
+                        "Line {} is not a {} bit binary number: {}",
+                        i + 1,
+                        width,
+                        line
+                    )));
+                }
+
+                Ok(u128::from_str_radix(line, 2)?)
+            })
+            .collect::<anyhow::Result<Vec<u128>>>()?;
+
+        Ok(Report { width, readings })
+    }
+}
+
+// Returns the `[zeros, ones]` count of a single column, given by its shift from the least significant bit.
+#[cfg(test)]
+fn count_column(readings: &[u128], shift: usize) -> [usize; 2] {
+    let ones = readings
+        .iter()
+        .filter(|reading| (*reading >> shift) & 1 == 1)
+        .count();
+
+    [readings.len() - ones, ones]
+}
 
-    counts
+#[cfg(test)]
+mod tests {
+    use super::rng::Lcg;
+    use super::*;
+
+    fn parse(lines: &[&str]) -> Report {
+        let lines = lines.iter().map(|l| l.to_string()).collect::<Vec<String>>();
+        Report::try_from(lines.as_slice()).unwrap()
+    }
+
+    const EXAMPLE: [&str; 12] = [
+        "00100", "11110", "10110", "10111", "10101", "01111", "00111", "11100", "10000", "11001",
+        "00010", "01010",
+    ];
+
+    #[test]
+    pub fn test_example() {
+        let report = parse(&EXAMPLE);
+        assert_eq!(part1(&report).unwrap(), 198);
+        assert_eq!(part2(&report).unwrap(), 230);
+    }
+
+    #[test]
+    pub fn test_rating_criteria() {
+        let report = parse(&EXAMPLE);
+        assert_eq!(rating(&report, RatingCriteria::OXYGEN_GENERATOR), Ok(23));
+        assert_eq!(rating(&report, RatingCriteria::CO2_SCRUBBER), Ok(10));
+
+        let ties = parse(&["10", "01"]);
+        for tie_break in [0, 1] {
+            let criteria = RatingCriteria {
+                criterion: BitCriterion::MostCommon,
+                tie_break,
+            };
+            assert_eq!(
+                rating(&ties, criteria),
+                Ok(tie_break << 1 | (tie_break ^ 1))
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_rating_errors() {
+        let report = parse(&["11", "10"]);
+        assert_eq!(
+            rating(&report, RatingCriteria::CO2_SCRUBBER),
+            Err(RatingError::NoCandidates { column: 0 })
+        );
+
+        let report = parse(&["11", "11"]);
+        assert_eq!(
+            rating(&report, RatingCriteria::OXYGEN_GENERATOR),
+            Err(RatingError::Ambiguous { remaining: 2 })
+        );
+    }
+
+    #[test]
+    pub fn test_sorted_ratings_match_reference() {
+        let criteria = [
+            RatingCriteria::OXYGEN_GENERATOR,
+            RatingCriteria::CO2_SCRUBBER,
+            RatingCriteria {
+                criterion: BitCriterion::MostCommon,
+                tie_break: 0,
+            },
+            RatingCriteria {
+                criterion: BitCriterion::LeastCommon,
+                tie_break: 1,
+            },
+        ];
+
+        let mut rng = Lcg::new(0x2021);
+        for (count, width) in [(12, 5), (1000, 12), (20000, 16), (5000, 40)] {
+            let readings = (0..count).map(|_| rng.below(1 << width) as u128).collect();
+            let report = Report { width, readings };
+
+            for criteria in criteria {
+                assert_eq!(
+                    sorted_ratings(&report, [criteria]).map(|[rating]| rating),
+                    rating(&report, criteria)
+                );
+            }
+        }
+
+        assert_eq!(
+            sorted_ratings(&parse(&EXAMPLE), [criteria[0], criteria[1]]),
+            Ok([23, 10])
+        );
+    }
+
+    #[test]
+    pub fn test_breakdown() {
+        let breakdown = Breakdown::new(&parse(&EXAMPLE));
+
+        assert_eq!(breakdown.columns.len(), 5);
+        assert_eq!(breakdown.columns[0].majority(), Some(1));
+        assert_eq!(breakdown.columns[1].majority(), Some(0));
+        assert!((breakdown.columns[0].balance() - 5.0 / 6.0).abs() < 1e-9);
+
+        let output = breakdown.to_string();
+        assert!(output.contains("gamma    10110 (22)"));
+        assert!(output.contains("epsilon  01001 (9)"));
+        assert!(output.contains("oxygen   10111 (23)"));
+        assert!(output.contains("co2      01010 (10)"));
+
+        let breakdown = Breakdown::new(&parse(&["11", "11"]));
+        assert_eq!(breakdown.columns[0].majority(), Some(1));
+        assert_eq!(breakdown.columns[0].balance(), 0.0);
+        assert!(breakdown.oxygen.is_err());
+        assert!(breakdown.to_string().contains("oxygen   error:"));
+    }
+
+    #[test]
+    pub fn test_wide_readings() {
+        let wide = format!("1{}", "0".repeat(99));
+        let report = parse(&[&wide, &wide, &"1".repeat(100)]);
+
+        assert_eq!(report.width, 100);
+        assert_eq!(report.count_bits()[0], [0, 3]);
+        assert_eq!(report.count_bits()[99], [2, 1]);
+    }
+
+    #[test]
+    pub fn test_invalid_readings() {
+        for lines in [vec!["101", "10"], vec!["101", "121"], vec![]] {
+            let lines = lines.iter().map(|l| l.to_string()).collect::<Vec<String>>();
+            assert!(Report::try_from(lines.as_slice()).is_err());
+        }
+    }
 }
@@ -1,90 +1,432 @@
 use std::{
-    collections::HashSet,
-    fs::File,
-    io::{BufRead, BufReader},
+    collections::{HashMap, HashSet},
+    fs::{File, OpenOptions},
+    io::Write,
     str::FromStr,
 };
 
+use anyhow::Context;
+use flags::take_flag;
+use replay::{render_draw, verify_log};
+use rules::{get_rule, AnyOf, CustomPatterns, Pattern, RowsAndColumns, WinRule};
+
+#[path = "../flags.rs"]
+mod flags;
+#[path = "../manifest.rs"]
+mod manifest;
+mod replay;
+#[cfg(test)]
+#[path = "../rng.rs"]
+mod rng;
+mod rules;
+
 fn main() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1).collect::<Vec<String>>();
+
     let input = get_input()?;
-    println!("Part 1 result: {}", part1(&input)?);
-    println!("Part 2 result: {}", part2(&input)?);
+    let result = simulate(&input, &RowsAndColumns);
+
+    println!("Part 1 result: {}", part1(&result)?);
+    println!("Part 2 result: {}", part2(&result)?);
+
+    // Any other rules replace the standard one for reporting the k-th finisher and replays.
+    let mut rules = Vec::new();
+    if let Some(names) = take_flag(&mut args, "--rules")? {
+        for name in names.split(',') {
+            rules.push(get_rule(name.trim())?);
+        }
+    }
+    if let Some(path) = take_flag(&mut args, "--patterns")? {
+        rules.push(Box::new(CustomPatterns::load(&path)?));
+    }
+
+    let rule: Box<dyn WinRule> = if rules.is_empty() {
+        Box::new(RowsAndColumns)
+    } else {
+        Box::new(AnyOf(rules))
+    };
+    let result = simulate(&input, rule.as_ref());
+
+    let render = match args.iter().position(|arg| arg == "--replay") {
+        Some(idx) => {
+            args.remove(idx);
+            true
+        }
+        None => false,
+    };
+    let verify = take_flag(&mut args, "--verify")?;
+    let resume = take_flag(&mut args, "--resume")?;
+    let log = take_flag(&mut args, "--log")?;
+
+    if let Some(path) = verify {
+        let mut game = Game::new(&input, rule.as_ref());
+        let draws = verify_log(&mut game, &std::fs::read_to_string(&path)?)?;
+        println!("Verified {} draws from {}", draws, path);
+    }
+
+    if render || resume.is_some() || log.is_some() {
+        let mut game = Game::new(&input, rule.as_ref());
+
+        // Resuming checks the existing log is a prefix of this game, then carries on appending to it.
+        let mut writer: Option<Box<dyn Write>> = match (resume, log) {
+            (Some(path), _) => {
+                let draws = verify_log(&mut game, &std::fs::read_to_string(&path)?)?;
+                println!("Resuming from draw {} of {}", draws, path);
+                Some(Box::new(OpenOptions::new().append(true).open(&path)?))
+            }
+            (None, Some(path)) => Some(Box::new(File::create(&path)?)),
+            (None, None) => None,
+        };
+
+        while let Some(event) = game.step() {
+            if render {
+                print!("{}", render_draw(&game, &event));
+            }
+            if let Some(writer) = writer.as_mut() {
+                writeln!(writer, "{}", event.to_json())?;
+            }
+        }
+    }
+
+    if let Some(k) = args.first() {
+        let k = usize::from_str(k)?;
+        match k.checked_sub(1).and_then(|idx| result.wins.get(idx)) {
+            Some(win) => println!(
+                "Board {} finishes #{} on draw {} (number {}) with {}, scoring {}",
+                win.board, k, win.draw, win.number, win.pattern, win.score
+            ),
+            None => println!("Only {} boards finish", result.wins.len()),
+        }
+    }
+
     Ok(())
 }
 
-fn part1(game_state: &GameState) -> anyhow::Result<u64> {
+fn part1(result: &GameResult) -> anyhow::Result<u64> {
+    result
+        .wins
+        .first()
+        .map(|win| win.score)
+        .context("Failed to find a winning board")
+}
+
+fn part2(result: &GameResult) -> anyhow::Result<u64> {
+    result
+        .wins
+        .last()
+        .map(|win| win.score)
+        .context("Failed to find a losing board")
+}
+
+// Plays through every draw, recording each board as it wins under the given rule. Boards winning on the same
+// draw are recorded in board order, boards which never win don't appear at all.
+fn simulate(game_state: &GameState, rule: &dyn WinRule) -> GameResult {
+    let mut game = Game::new(game_state, rule);
+    let mut wins = Vec::new();
+
+    while !game.is_finished() {
+        match game.step() {
+            Some(event) => wins.extend(event.wins),
+            None => break,
+        }
+    }
+
+    GameResult { wins }
+}
+
+// A game in progress, advanced one draw at a time.
+//
+// Rather than rechecking every board after each draw, we look up the cells holding the drawn number and count
+// the marks in each pattern they're part of. A draw then only costs the cells it marks.
+struct Game<'a> {
+    state: &'a GameState,
+    index: MarkingIndex,
+    called_nums: HashSet<u64>,
+    won: Vec<bool>,
+    wins: usize,
+    next_draw: usize,
+}
+
+impl<'a> Game<'a> {
+    pub fn new(state: &'a GameState, rule: &dyn WinRule) -> Self {
+        Game {
+            state,
+            index: MarkingIndex::new(&state.boards, rule),
+            called_nums: HashSet::new(),
+            won: vec![false; state.boards.len()],
+            wins: 0,
+            next_draw: 0,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.wins == self.state.boards.len()
+    }
+
+    pub fn is_called(&self, num: u64) -> bool {
+        self.called_nums.contains(&num)
+    }
+
+    pub fn has_won(&self, board: usize) -> bool {
+        self.won[board]
+    }
+
+    // Calls the next number, returning what it changed, or None once every number has been called.
+    pub fn step(&mut self) -> Option<DrawEvent> {
+        let draw = self.next_draw;
+        let num = *self.state.numbers.get(draw)?;
+        self.next_draw += 1;
+
+        let mut event = DrawEvent {
+            draw,
+            number: num,
+            affected: Vec::new(),
+            wins: Vec::new(),
+        };
+
+        // Calling a number a second time doesn't mark anything new.
+        if !self.called_nums.insert(num) {
+            return Some(event);
+        }
+
+        let (affected, mut completed) = self.index.mark(num, &self.won);
+        event.affected = affected;
+
+        // A board can complete several patterns at once, so report the one the rule lists first.
+        completed.sort_unstable();
+        completed.dedup_by_key(|(board, _)| *board);
+
+        for (board, pattern) in completed {
+            self.won[board] = true;
+            self.wins += 1;
+            event.wins.push(WinEvent {
+                board,
+                draw,
+                number: num,
+                pattern: self.index.pattern(board, pattern).clone(),
+                score: num
+                    * self.state.boards[board]
+                        .get_unmarked_nums(&self.called_nums)
+                        .sum::<u64>(),
+            });
+        }
+
+        Some(event)
+    }
+}
+
+// Everything that happened on a single draw.
+struct DrawEvent {
+    pub draw: usize,
+    pub number: u64,
+    // Boards which had a cell marked, in board order.
+    pub affected: Vec<usize>,
+    pub wins: Vec<WinEvent>,
+}
+
+// Plays through every draw by rescanning each remaining board, used to check `simulate` against.
+#[cfg(test)]
+fn simulate_by_scanning(game_state: &GameState, rule: &dyn WinRule) -> GameResult {
     let mut called_nums = HashSet::new();
+    let mut remaining = (0..game_state.boards.len()).collect::<Vec<usize>>();
+    let mut wins = Vec::new();
+
+    for (draw, num) in game_state.numbers.iter().enumerate() {
+        if remaining.is_empty() {
+            break;
+        }
 
-    for num in &game_state.numbers {
         called_nums.insert(*num);
 
-        for board in &game_state.boards {
-            if board.has_won(&called_nums) {
-                return Ok(num * board.get_unmarked_nums(&called_nums).sum::<u64>());
+        remaining.retain(|idx| {
+            let board = &game_state.boards[*idx];
+
+            match board.winning_pattern(rule, &called_nums) {
+                Some(pattern) => {
+                    wins.push(WinEvent {
+                        board: *idx,
+                        draw,
+                        number: *num,
+                        pattern,
+                        score: num * board.get_unmarked_nums(&called_nums).sum::<u64>(),
+                    });
+                    false
+                }
+                None => true,
+            }
+        });
+    }
+
+    GameResult { wins }
+}
+
+// The patterns a rule gives for one board size, along with the patterns each cell is part of.
+struct Layout {
+    patterns: Vec<(Pattern, usize)>,
+    cell_patterns: Vec<Vec<usize>>,
+}
+
+impl Layout {
+    pub fn new(rule: &dyn WinRule, width: usize, height: usize) -> Self {
+        let mut patterns = Vec::new();
+        let mut cell_patterns = vec![Vec::new(); width * height];
+
+        // A pattern without cells could never be completed by a draw, so leave it out.
+        for (pattern, cells) in rule.patterns(width, height) {
+            if cells.is_empty() {
+                continue;
             }
+
+            for (row, column) in &cells {
+                cell_patterns[row * width + column].push(patterns.len());
+            }
+            patterns.push((pattern, cells.len()));
+        }
+
+        Layout {
+            patterns,
+            cell_patterns,
         }
     }
+}
 
-    Err(anyhow::anyhow!("Failed to find a winning board"))
+// Maps each number to the cells holding it, alongside a count of the marked cells in every pattern. Boards of
+// the same size share a layout, so large games only pay for the counts.
+struct MarkingIndex {
+    cells: HashMap<u64, Vec<(usize, usize)>>,
+    layouts: Vec<Layout>,
+    board_layouts: Vec<usize>,
+    hits: Vec<Vec<usize>>,
 }
 
-fn part2(game_state: &GameState) -> anyhow::Result<u64> {
-    let mut called_nums = HashSet::new();
-    let mut candidate_boards = game_state.boards.clone();
+impl MarkingIndex {
+    pub fn new(boards: &[Board], rule: &dyn WinRule) -> Self {
+        let mut cells: HashMap<u64, Vec<(usize, usize)>> = HashMap::new();
+        let mut layouts = Vec::new();
+        let mut layout_sizes = HashMap::new();
+        let mut board_layouts = Vec::with_capacity(boards.len());
+        let mut hits = Vec::with_capacity(boards.len());
 
-    for num in &game_state.numbers {
-        called_nums.insert(*num);
+        for (board_idx, board) in boards.iter().enumerate() {
+            let (width, height) = (board.width(), board.grid.len());
+            let layout = *layout_sizes.entry((width, height)).or_insert_with(|| {
+                layouts.push(Layout::new(rule, width, height));
+                layouts.len() - 1
+            });
 
-        if candidate_boards.len() > 1 {
-            called_nums.insert(*num);
+            board_layouts.push(layout);
+            hits.push(vec![0; layouts[layout].patterns.len()]);
 
-            candidate_boards = candidate_boards
-                .into_iter()
-                .filter(|b| !b.has_won(&called_nums))
-                .collect();
-        } else {
-            let last_board = &candidate_boards[0];
+            for (cell, num) in board.grid.iter().flatten().enumerate() {
+                cells.entry(*num).or_default().push((board_idx, cell));
+            }
+        }
 
-            if last_board.has_won(&called_nums) {
-                return Ok(num * last_board.get_unmarked_nums(&called_nums).sum::<u64>());
+        MarkingIndex {
+            cells,
+            layouts,
+            board_layouts,
+            hits,
+        }
+    }
+
+    pub fn pattern(&self, board: usize, pattern: usize) -> &Pattern {
+        &self.layouts[self.board_layouts[board]].patterns[pattern].0
+    }
+
+    // Marks every cell holding `num` on boards which haven't won yet, returning the boards this touched and the
+    // patterns it completed.
+    pub fn mark(&mut self, num: u64, won: &[bool]) -> (Vec<usize>, Vec<(usize, usize)>) {
+        let mut affected: Vec<usize> = Vec::new();
+        let mut completed = Vec::new();
+
+        for (board, cell) in self.cells.get(&num).into_iter().flatten() {
+            if won[*board] {
+                continue;
+            }
+
+            // Cells are indexed in board order, so repeats are always adjacent.
+            if affected.last() != Some(board) {
+                affected.push(*board);
+            }
+
+            let layout = &self.layouts[self.board_layouts[*board]];
+            for pattern in &layout.cell_patterns[*cell] {
+                self.hits[*board][*pattern] += 1;
+                if self.hits[*board][*pattern] == layout.patterns[*pattern].1 {
+                    completed.push((*board, *pattern));
+                }
             }
         }
+
+        (affected, completed)
     }
+}
 
-    Err(anyhow::anyhow!("Failed to find a losing board"))
+struct GameResult {
+    // Every board which won, in the order they won.
+    pub wins: Vec<WinEvent>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct WinEvent {
+    pub board: usize,
+    pub draw: usize,
+    pub number: u64,
+    pub pattern: Pattern,
+    pub score: u64,
 }
 
 fn get_input() -> anyhow::Result<GameState> {
-    const PATH: &str = "src/day04/input.txt";
+    manifest::open("day04", None, |data| {
+        let lines = data.lines().map(str::to_string).collect::<Vec<String>>();
 
-    let file = File::open(PATH)?;
-    let reader = BufReader::new(file);
-    let lines = reader.lines().collect::<Result<Vec<String>, _>>()?;
+        parse_game(&lines)
+    })
+}
 
-    let numbers = lines[0]
-        .split(",")
-        .map(|num| u64::from_str(num))
+fn parse_game(lines: &[String]) -> anyhow::Result<GameState> {
+    let numbers = lines
+        .first()
+        .context("Input is empty")?
+        .split(',')
+        .map(u64::from_str)
         .collect::<Result<Vec<u64>, _>>()?;
 
     let mut boards = Vec::new();
+    let mut block = Vec::new();
 
-    for raw_grid in lines[1..]
-        .iter()
-        .filter(|line| !line.is_empty())
-        .collect::<Vec<&String>>()
-        .chunks(5)
-    {
-        let nums = raw_grid
-            .iter()
-            .map(|line| {
-                line.split(" ")
-                    .filter(|num| !num.is_empty()) // Filter out empty strings as the grids are formatted with additional whitespace
-                    .map(|num| u64::from_str(num))
-                    .collect::<Result<Vec<u64>, _>>()
-            })
-            .collect::<Result<Vec<Vec<u64>>, _>>()?;
+    // Boards are separated by blank lines, their size is whatever the block of lines holds.
+    for (i, line) in lines.iter().enumerate().skip(1) {
+        if line.trim().is_empty() {
+            if !block.is_empty() {
+                boards.push(Board::new(std::mem::take(&mut block))?);
+            }
+            continue;
+        }
+
+        let row = line
+            .split_whitespace()
+            .map(u64::from_str)
+            .collect::<Result<Vec<u64>, _>>()
+            .with_context(|| format!("Failed to parse board row on line {}", i + 1))?;
+
+        if let Some(first) = block.first() {
+            if first.len() != row.len() {
+                return Err(anyhow::anyhow!(
+                    "Board row on line {} has {} numbers, expected {}",
+                    i + 1,
+                    row.len(),
+                    first.len()
+                ));
+            }
+        }
+
+        block.push(row);
+    }
 
-        boards.push(Board { grid: nums })
+    if !block.is_empty() {
+        boards.push(Board::new(block)?);
     }
 
     Ok(GameState { numbers, boards })
@@ -92,36 +434,50 @@ fn get_input() -> anyhow::Result<GameState> {
 
 struct GameState {
     pub numbers: Vec<u64>,
-    pub boards: Vec<Board<5>>,
+    pub boards: Vec<Board>,
 }
 
 #[derive(Clone)]
-struct Board<const T: usize> {
+struct Board {
     pub grid: Vec<Vec<u64>>,
 }
 
-impl<const T: usize> Board<T> {
-    pub fn has_won(&self, called_numbers: &HashSet<u64>) -> bool {
-        // First check rows:
-        for row in &self.grid {
-            if row.iter().all(|num| called_numbers.contains(num)) {
-                return true;
-            }
+impl Board {
+    pub fn new(grid: Vec<Vec<u64>>) -> anyhow::Result<Self> {
+        let width = grid.first().map_or(0, |row| row.len());
+
+        if width == 0 {
+            return Err(anyhow::anyhow!("Boards must have at least one number"));
         }
 
-        // Now check columns
-        for index in 0..T {
-            if self
-                .grid
-                .iter()
-                .map(|row| row[index])
-                .all(|num| called_numbers.contains(&num))
-            {
-                return true;
-            }
+        if grid.iter().any(|row| row.len() != width) {
+            return Err(anyhow::anyhow!(
+                "Every row of a board must be the same length"
+            ));
         }
 
-        false
+        Ok(Board { grid })
+    }
+
+    pub fn width(&self) -> usize {
+        self.grid[0].len()
+    }
+
+    #[cfg(test)]
+    pub fn winning_pattern(
+        &self,
+        rule: &dyn WinRule,
+        called_numbers: &HashSet<u64>,
+    ) -> Option<Pattern> {
+        rule.patterns(self.width(), self.grid.len())
+            .into_iter()
+            .filter(|(_, cells)| !cells.is_empty())
+            .find(|(_, cells)| {
+                cells
+                    .iter()
+                    .all(|(row, column)| called_numbers.contains(&self.grid[*row][*column]))
+            })
+            .map(|(pattern, _)| pattern)
     }
 
     pub fn get_unmarked_nums<'a, 'b: 'a>(
@@ -130,7 +486,170 @@ impl<const T: usize> Board<T> {
     ) -> impl Iterator<Item = &'a u64> {
         self.grid
             .iter()
-            .flat_map(|row| row)
+            .flatten()
             .filter(move |num| !called_numbers.contains(num))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::rng::Lcg;
+    use super::rules::{Blackout, Diagonals, FourCorners};
+    use super::*;
+
+    fn lines(input: &str) -> Vec<String> {
+        input.lines().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    pub fn test_board_sizes_from_input() {
+        let game = parse_game(&lines(
+            "1,2,3\n\n1 2 3\n4 5 6\n7 8 9\n\n10 11\n12 13\n14 15\n",
+        ))
+        .unwrap();
+
+        assert_eq!(game.boards.len(), 2);
+        assert_eq!((game.boards[0].width(), game.boards[0].grid.len()), (3, 3));
+        assert_eq!((game.boards[1].width(), game.boards[1].grid.len()), (2, 3));
+        assert_eq!(
+            part1(&simulate(&game, &RowsAndColumns)).unwrap(),
+            3 * (4 + 5 + 6 + 7 + 8 + 9)
+        );
+    }
+
+    #[test]
+    pub fn test_win_order() {
+        let game = parse_game(&lines(
+            "5,1,4,2,9,3\n\n1 2\n3 4\n\n5 6\n7 8\n\n1 3\n5 9\n\n9 10\n11 12\n\n4 2\n30 31\n",
+        ))
+        .unwrap();
+        let result = simulate(&game, &RowsAndColumns);
+
+        assert_eq!(
+            result.wins,
+            vec![
+                WinEvent {
+                    board: 2,
+                    draw: 1,
+                    number: 1,
+                    pattern: Pattern::Column(0),
+                    score: 3 + 9,
+                },
+                WinEvent {
+                    board: 0,
+                    draw: 3,
+                    number: 2,
+                    pattern: Pattern::Row(0),
+                    score: 2 * 3,
+                },
+                WinEvent {
+                    board: 4,
+                    draw: 3,
+                    number: 2,
+                    pattern: Pattern::Row(0),
+                    score: 2 * (30 + 31),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_ragged_board_rejected() {
+        let err = parse_game(&lines("1,2\n\n1 2 3\n4 5\n")).err().unwrap();
+        assert!(err.to_string().contains("line 4"), "{}", err);
+    }
+
+    #[test]
+    pub fn test_indexed_simulation_matches_scanning() {
+        let mut rng = Lcg::new(0x2021);
+
+        for (boards, width, height, range) in [(500, 5, 5, 100), (200, 3, 7, 40), (50, 7, 7, 60)] {
+            let numbers = (0..range * 2).map(|_| rng.below(range)).collect();
+            let boards = (0..boards)
+                .map(|_| Board {
+                    grid: (0..height)
+                        .map(|_| (0..width).map(|_| rng.below(range)).collect())
+                        .collect(),
+                })
+                .collect();
+            let game = GameState { numbers, boards };
+
+            let rule = AnyOf(vec![
+                Box::new(RowsAndColumns),
+                Box::new(Diagonals),
+                Box::new(FourCorners),
+            ]);
+
+            assert_eq!(
+                simulate(&game, &rule).wins,
+                simulate_by_scanning(&game, &rule).wins
+            );
+            assert_eq!(
+                simulate(&game, &Blackout).wins,
+                simulate_by_scanning(&game, &Blackout).wins
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_custom_patterns() {
+        let patterns =
+            CustomPatterns::parse("# Comments are skipped\nvee:\nX.X\n.X.\n...\n\n.X\nX.\n")
+                .unwrap();
+        assert_eq!(patterns.masks.len(), 2);
+        assert_eq!(patterns.masks[1].0, "pattern 2");
+
+        let game = parse_game(&lines("5,1,3,7,2,4\n\n1 2 3\n4 5 6\n7 8 9\n\n1 2\n3 4\n")).unwrap();
+        let result = simulate(&game, &patterns);
+
+        assert_eq!(
+            result
+                .wins
+                .iter()
+                .map(|win| (win.board, win.draw, win.pattern.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (0, 2, Pattern::Custom("vee".to_string())),
+                (1, 4, Pattern::Custom("pattern 2".to_string())),
+            ]
+        );
+
+        assert!(CustomPatterns::parse("X.\nX\n").is_err());
+        assert!(CustomPatterns::parse("...\n").is_err());
+        assert!(CustomPatterns::parse("X?\n").is_err());
+    }
+
+    #[test]
+    pub fn test_replay_log() {
+        let game_state = parse_game(&lines("1,5,9,2,3\n\n1 2\n3 4\n\n5 9\n7 1\n")).unwrap();
+
+        let mut game = Game::new(&game_state, &RowsAndColumns);
+        let log = std::iter::from_fn(|| game.step())
+            .map(|event| event.to_json())
+            .collect::<Vec<String>>();
+
+        assert_eq!(
+            log[2],
+            "{\"draw\":2,\"number\":9,\"affected\":[1],\"wins\":[{\"board\":1,\"pattern\":\"row 0\",\"score\":63}]}"
+        );
+
+        // Any prefix of the log can be verified, leaving the game ready to carry on from there.
+        let mut game = Game::new(&game_state, &RowsAndColumns);
+        assert_eq!(verify_log(&mut game, &log[..3].join("\n")).unwrap(), 3);
+
+        let event = game.step().unwrap();
+        assert_eq!(event.to_json(), log[3]);
+        assert_eq!(
+            render_draw(&game, &event),
+            "Draw 3: 2\nBoard 0 - wins with row 0, scoring 14\n[1] [2]\n 3   4\nBoard 1 - won\n[5] [9]\n 7  [1]\n\n"
+        );
+
+        let mut game = Game::new(&game_state, &RowsAndColumns);
+        let tampered = log[..2].join("\n").replace("\"number\":5", "\"number\":6");
+        assert!(verify_log(&mut game, &tampered).is_err());
+
+        let mut game = Game::new(&game_state, &RowsAndColumns);
+        let extended = format!("{}\n{}", log.join("\n"), log[0]);
+        assert!(verify_log(&mut game, &extended).is_err());
+    }
+}
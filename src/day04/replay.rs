@@ -0,0 +1,122 @@
+use super::{DrawEvent, Game};
+
+// Renders every board as it stands after the draw, with called numbers in brackets.
+pub fn render_draw(game: &Game, event: &DrawEvent) -> String {
+    let mut output = format!("Draw {}: {}\n", event.draw, event.number);
+
+    for (idx, board) in game.state.boards.iter().enumerate() {
+        let status = match event.wins.iter().find(|win| win.board == idx) {
+            Some(win) => format!(" - wins with {}, scoring {}", win.pattern, win.score),
+            None if game.has_won(idx) => " - won".to_string(),
+            None => String::new(),
+        };
+        output.push_str(&format!("Board {}{}\n", idx, status));
+
+        let width = board
+            .grid
+            .iter()
+            .flatten()
+            .map(|num| num.to_string().len())
+            .max()
+            .unwrap_or(1);
+
+        for row in &board.grid {
+            let cells = row
+                .iter()
+                .map(|num| {
+                    if game.is_called(*num) {
+                        format!("[{:>width$}]", num, width = width)
+                    } else {
+                        format!(" {:>width$} ", num, width = width)
+                    }
+                })
+                .collect::<Vec<String>>();
+            output.push_str(cells.join(" ").trim_end());
+            output.push('\n');
+        }
+    }
+
+    output.push('\n');
+    output
+}
+
+impl DrawEvent {
+    // A single line of JSON, so a log holds one draw per line.
+    pub fn to_json(&self) -> String {
+        let affected = self
+            .affected
+            .iter()
+            .map(|board| board.to_string())
+            .collect::<Vec<String>>();
+        let wins = self
+            .wins
+            .iter()
+            .map(|win| {
+                format!(
+                    "{{\"board\":{},\"pattern\":\"{}\",\"score\":{}}}",
+                    win.board,
+                    escape(&win.pattern.to_string()),
+                    win.score
+                )
+            })
+            .collect::<Vec<String>>();
+
+        format!(
+            "{{\"draw\":{},\"number\":{},\"affected\":[{}],\"wins\":[{}]}}",
+            self.draw,
+            self.number,
+            affected.join(","),
+            wins.join(",")
+        )
+    }
+}
+
+// Plays the game forward through every draw in the log, checking each matches what the game produces. On
+// success the game is left just after the last logged draw, ready to carry on from there.
+pub fn verify_log(game: &mut Game, log: &str) -> anyhow::Result<usize> {
+    let mut draws = 0;
+
+    for (i, line) in log.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let event = game.step().ok_or_else(|| {
+            anyhow::anyhow!(
+                "Log line {} is past the last draw of the game ({} draws)",
+                i + 1,
+                draws
+            )
+        })?;
+
+        let expected = event.to_json();
+        if line != expected {
+            return Err(anyhow::anyhow!(
+                "Log diverges from the game on line {}:\n  expected: {}\n     found: {}",
+                i + 1,
+                expected,
+                line
+            ));
+        }
+
+        draws += 1;
+    }
+
+    Ok(draws)
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
@@ -0,0 +1,234 @@
+use std::{fmt::Display, fs};
+
+// A `(row, column)` position on a board.
+pub type Cell = (usize, usize);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pattern {
+    Row(usize),
+    Column(usize),
+    Diagonal,
+    AntiDiagonal,
+    Corners,
+    Blackout,
+    Custom(String),
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pattern::Row(row) => write!(f, "row {}", row),
+            Pattern::Column(column) => write!(f, "column {}", column),
+            Pattern::Diagonal => write!(f, "diagonal"),
+            Pattern::AntiDiagonal => write!(f, "anti-diagonal"),
+            Pattern::Corners => write!(f, "corners"),
+            Pattern::Blackout => write!(f, "blackout"),
+            Pattern::Custom(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+pub trait WinRule {
+    // Every group of cells which wins once fully marked, for a board of the given size. When several patterns
+    // complete on the same draw, the one listed first is reported.
+    fn patterns(&self, width: usize, height: usize) -> Vec<(Pattern, Vec<Cell>)>;
+}
+
+// The standard rule: any full row or column.
+pub struct RowsAndColumns;
+
+impl WinRule for RowsAndColumns {
+    fn patterns(&self, width: usize, height: usize) -> Vec<(Pattern, Vec<Cell>)> {
+        let rows =
+            (0..height).map(|row| (Pattern::Row(row), (0..width).map(|c| (row, c)).collect()));
+        let columns = (0..width).map(|column| {
+            (
+                Pattern::Column(column),
+                (0..height).map(|r| (r, column)).collect(),
+            )
+        });
+
+        rows.chain(columns).collect()
+    }
+}
+
+// Both diagonals, only for square boards.
+pub struct Diagonals;
+
+impl WinRule for Diagonals {
+    fn patterns(&self, width: usize, height: usize) -> Vec<(Pattern, Vec<Cell>)> {
+        if width != height {
+            return Vec::new();
+        }
+
+        vec![
+            (Pattern::Diagonal, (0..width).map(|i| (i, i)).collect()),
+            (
+                Pattern::AntiDiagonal,
+                (0..width).map(|i| (i, width - 1 - i)).collect(),
+            ),
+        ]
+    }
+}
+
+pub struct FourCorners;
+
+impl WinRule for FourCorners {
+    fn patterns(&self, width: usize, height: usize) -> Vec<(Pattern, Vec<Cell>)> {
+        let mut cells = vec![
+            (0, 0),
+            (0, width - 1),
+            (height - 1, 0),
+            (height - 1, width - 1),
+        ];
+        // Boards a single cell wide or tall share corners.
+        cells.sort_unstable();
+        cells.dedup();
+
+        vec![(Pattern::Corners, cells)]
+    }
+}
+
+pub struct Blackout;
+
+impl WinRule for Blackout {
+    fn patterns(&self, width: usize, height: usize) -> Vec<(Pattern, Vec<Cell>)> {
+        let cells = (0..height)
+            .flat_map(|row| (0..width).map(move |column| (row, column)))
+            .collect();
+
+        vec![(Pattern::Blackout, cells)]
+    }
+}
+
+// Named masks of cells, each only applying to boards of the same size as the mask.
+//
+// Masks are separated by blank lines, with `X` marking the cells that need to be called and `.` those that
+// don't. A mask can be named by a `name:` line above it, lines starting with `#` are ignored.
+pub struct CustomPatterns {
+    pub masks: Vec<(String, Vec<Vec<bool>>)>,
+}
+
+impl CustomPatterns {
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let source = fs::read_to_string(path)?;
+        Self::parse(&source).map_err(|e| anyhow::anyhow!("Failed to load patterns {}: {}", path, e))
+    }
+
+    pub fn parse(source: &str) -> anyhow::Result<Self> {
+        let mut masks = Vec::new();
+        let mut name = None;
+        let mut mask: Vec<Vec<bool>> = Vec::new();
+
+        let lines = source.lines().map(str::trim).chain(std::iter::once(""));
+        for (i, line) in lines.enumerate() {
+            if line.starts_with('#') {
+                continue;
+            }
+
+            if line.is_empty() {
+                if !mask.is_empty() {
+                    if !mask.iter().flatten().any(|cell| *cell) {
+                        return Err(anyhow::anyhow!(
+                            "Pattern ending on line {} has no marked cells",
+                            i
+                        ));
+                    }
+
+                    let name = name
+                        .take()
+                        .unwrap_or_else(|| format!("pattern {}", masks.len() + 1));
+                    masks.push((name, std::mem::take(&mut mask)));
+                } else if name.is_some() {
+                    return Err(anyhow::anyhow!("Pattern named on line {} is empty", i));
+                }
+
+                continue;
+            }
+
+            if let Some(label) = line.strip_suffix(':') {
+                if !mask.is_empty() || name.is_some() {
+                    return Err(anyhow::anyhow!("Unexpected pattern name on line {}", i + 1));
+                }
+
+                name = Some(label.trim().to_string());
+                continue;
+            }
+
+            let row = line
+                .chars()
+                .map(|c| match c {
+                    'X' | 'x' => Ok(true),
+                    '.' => Ok(false),
+                    _ => Err(anyhow::anyhow!(
+                        "Unexpected character '{}' on line {}",
+                        c,
+                        i + 1
+                    )),
+                })
+                .collect::<anyhow::Result<Vec<bool>>>()?;
+
+            if mask.first().is_some_and(|first| first.len() != row.len()) {
+                return Err(anyhow::anyhow!(
+                    "Pattern row on line {} has {} cells, expected {}",
+                    i + 1,
+                    row.len(),
+                    mask[0].len()
+                ));
+            }
+
+            mask.push(row);
+        }
+
+        Ok(CustomPatterns { masks })
+    }
+}
+
+impl WinRule for CustomPatterns {
+    fn patterns(&self, width: usize, height: usize) -> Vec<(Pattern, Vec<Cell>)> {
+        self.masks
+            .iter()
+            .filter(|(_, mask)| mask.len() == height && mask[0].len() == width)
+            .map(|(name, mask)| {
+                let cells = mask
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(row, cells)| {
+                        cells
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, marked)| **marked)
+                            .map(move |(column, _)| (row, column))
+                    })
+                    .collect();
+
+                (Pattern::Custom(name.clone()), cells)
+            })
+            .collect()
+    }
+}
+
+// Wins as soon as any of the rules does, preferring the patterns of earlier rules.
+pub struct AnyOf(pub Vec<Box<dyn WinRule>>);
+
+impl WinRule for AnyOf {
+    fn patterns(&self, width: usize, height: usize) -> Vec<(Pattern, Vec<Cell>)> {
+        self.0
+            .iter()
+            .flat_map(|rule| rule.patterns(width, height))
+            .collect()
+    }
+}
+
+pub fn get_rule(name: &str) -> anyhow::Result<Box<dyn WinRule>> {
+    match name {
+        "lines" => Ok(Box::new(RowsAndColumns)),
+        "diagonals" => Ok(Box::new(Diagonals)),
+        "corners" => Ok(Box::new(FourCorners)),
+        "blackout" => Ok(Box::new(Blackout)),
+        _ => Err(anyhow::anyhow!(
+            "Unknown win rule: {} (expected one of: lines, diagonals, corners, blackout)",
+            name
+        )),
+    }
+}
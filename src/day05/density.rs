@@ -0,0 +1,141 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{BufWriter, Write},
+};
+
+use super::{Line, Point};
+
+// Rasterising visits every point of every line, so refuse inputs which would take too long or too much memory.
+const MAX_POINTS: u64 = 50_000_000;
+const MAX_IMAGE_SIDE: i64 = 8192;
+
+// How many lines cover each lattice point.
+pub struct Density {
+    pub counts: HashMap<Point<2>, u32>,
+}
+
+pub struct DensityStats {
+    pub max_overlap: u32,
+    // The number of points covered by exactly that many lines.
+    pub histogram: BTreeMap<u32, usize>,
+    // The smallest and largest corners of the box holding every covered point.
+    pub bounds: Option<(Point<2>, Point<2>)>,
+}
+
+impl Density {
+    pub fn new(lines: &[Line<2>]) -> anyhow::Result<Self> {
+        let total = lines.iter().map(Line::lattice_count).sum::<u64>();
+        if total > MAX_POINTS {
+            return Err(anyhow::anyhow!(
+                "Lines cover {} points, more than the {} a density field supports",
+                total,
+                MAX_POINTS
+            ));
+        }
+
+        let mut counts: HashMap<Point<2>, u32> = HashMap::new();
+        for line in lines {
+            for point in line.lattice_points() {
+                *counts.entry(point).or_insert(0) += 1;
+            }
+        }
+
+        Ok(Density { counts })
+    }
+
+    pub fn stats(&self) -> DensityStats {
+        let mut histogram = BTreeMap::new();
+        for count in self.counts.values() {
+            *histogram.entry(*count).or_insert(0) += 1;
+        }
+
+        let bounds = self.counts.keys().fold(None, |bounds, point| match bounds {
+            None => Some((*point, *point)),
+            Some((min, max)) => {
+                let ([x, y], [min_x, min_y], [max_x, max_y]) =
+                    (point.coords, min.coords, max.coords);
+                Some((
+                    Point {
+                        coords: [x.min(min_x), y.min(min_y)],
+                    },
+                    Point {
+                        coords: [x.max(max_x), y.max(max_y)],
+                    },
+                ))
+            }
+        });
+
+        DensityStats {
+            max_overlap: self.counts.values().copied().max().unwrap_or(0),
+            histogram,
+            bounds,
+        }
+    }
+
+    // One `x,y,count` row per covered point, ordered by row then column.
+    pub fn write_csv<W: Write>(&self, writer: W) -> anyhow::Result<()> {
+        let mut writer = BufWriter::new(writer);
+        let mut points = self.counts.iter().collect::<Vec<(&Point<2>, &u32)>>();
+        points.sort_unstable_by_key(|(point, _)| (point.coords[1], point.coords[0]));
+
+        writeln!(writer, "x,y,count")?;
+        for (point, count) in points {
+            let [x, y] = point.coords;
+            writeln!(writer, "{},{},{}", x, y, count)?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    // Writes the bounding box as a binary PPM image, one pixel per point. Uncovered points are black, and
+    // covered ones go from red through yellow to white as the overlap approaches the maximum.
+    pub fn write_ppm<W: Write>(&self, writer: W) -> anyhow::Result<()> {
+        let stats = self.stats();
+        let (min, max) = stats
+            .bounds
+            .ok_or_else(|| anyhow::anyhow!("No points to draw"))?;
+        let ([min_x, min_y], [max_x, max_y]) = (min.coords, max.coords);
+        let (width, height) = (max_x - min_x + 1, max_y - min_y + 1);
+
+        if width > MAX_IMAGE_SIDE || height > MAX_IMAGE_SIDE {
+            return Err(anyhow::anyhow!(
+                "A {}x{} heatmap is larger than the {} pixels a side supported",
+                width,
+                height,
+                MAX_IMAGE_SIDE
+            ));
+        }
+
+        let mut pixels = vec![0u8; (width * height * 3) as usize];
+        for (point, count) in &self.counts {
+            let [x, y] = point.coords;
+            let idx = (((y - min_y) * width + (x - min_x)) * 3) as usize;
+            pixels[idx..idx + 3].copy_from_slice(&heat(*count, stats.max_overlap));
+        }
+
+        let mut writer = BufWriter::new(writer);
+        write!(writer, "P6\n{} {}\n255\n", width, height)?;
+        writer.write_all(&pixels)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+fn heat(count: u32, max: u32) -> [u8; 3] {
+    let t = if max > 1 {
+        (count - 1) as f64 / (max - 1) as f64
+    } else {
+        1.0
+    };
+
+    // Split the ramp into thirds, bringing in red, then green, then blue.
+    let channel = |start: f64| ((t - start) * 3.0).clamp(0.0, 1.0);
+    let level = |value: f64| (64.0 + value * 191.0) as u8;
+
+    [
+        level(channel(0.0)),
+        (channel(1.0 / 3.0) * 255.0) as u8,
+        (channel(2.0 / 3.0) * 255.0) as u8,
+    ]
+}
@@ -1,48 +1,289 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fs::File,
-    io::{BufRead, BufReader},
     str::FromStr,
 };
 
-fn main() -> anyhow::Result<()> {
-    let input = get_input()?;
+use anyhow::Context;
+use density::Density;
+use flags::take_flag;
+
+mod density;
+#[path = "../flags.rs"]
+mod flags;
+#[path = "../manifest.rs"]
+mod manifest;
+#[cfg(test)]
+#[path = "../rng.rs"]
+mod rng;
 
-    println!("Part 1 result: {}", part1(&input));
-    println!("Part 2 result: {}", part2(&input));
+fn main() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1).collect::<Vec<String>>();
+    let path = take_flag(&mut args, "--input")?;
+    let heatmap = take_flag(&mut args, "--heatmap")?;
+    let density_csv = take_flag(&mut args, "--density-csv")?;
+    let any_slope = args.iter().any(|arg| arg == "--any-slope");
+    let stats = args.iter().any(|arg| arg == "--stats");
+
+    match get_input(path.as_deref())? {
+        Vents::Planar(input) => {
+            report(&input, any_slope);
+
+            if heatmap.is_some() || density_csv.is_some() || stats {
+                let mode = if any_slope {
+                    Mode::AnySlope
+                } else {
+                    Mode::Diagonal
+                };
+                let lines = input
+                    .iter()
+                    .filter(|line| mode.includes(line))
+                    .cloned()
+                    .collect::<Vec<Line<2>>>();
+                let density = Density::new(&lines)?;
+
+                if let Some(path) = heatmap {
+                    density.write_ppm(File::create(&path)?)?;
+                    println!("Wrote heatmap to {}", path);
+                }
+                if let Some(path) = density_csv {
+                    density.write_csv(File::create(&path)?)?;
+                    println!("Wrote density to {}", path);
+                }
+                if stats {
+                    let stats = density.stats();
+                    println!("Max overlap: {}", stats.max_overlap);
+                    if let Some((min, max)) = stats.bounds {
+                        let ([min_x, min_y], [max_x, max_y]) = (min.coords, max.coords);
+                        println!(
+                            "Bounding box: ({}, {}) to ({}, {})",
+                            min_x, min_y, max_x, max_y
+                        );
+                    }
+                    for (count, points) in stats.histogram {
+                        println!("  {} lines: {} points", count, points);
+                    }
+                }
+            }
+        }
+        Vents::Spatial(input) => {
+            if heatmap.is_some() || density_csv.is_some() || stats {
+                return Err(anyhow::anyhow!(
+                    "Density exports are only supported for 2D vents"
+                ));
+            }
+
+            report(&input, any_slope);
+        }
+    }
 
     Ok(())
 }
 
-fn part1(input: &Vec<Line>) -> usize {
-    let non_diagonal = input
-        .iter()
-        .filter(|line| line.is_horizontal() || line.is_vertical())
-        .collect::<Vec<&Line>>();
+fn report<const N: usize>(input: &[Line<N>], any_slope: bool) {
+    println!("Part 1 result: {}", part1(input));
+    println!("Part 2 result: {}", part2(input));
+
+    if any_slope {
+        println!(
+            "Any slope result: {}",
+            count_overlaps_with(input, Mode::AnySlope)
+        );
+        println!(
+            "Lattice points on lines: {}",
+            input.iter().map(Line::lattice_count).sum::<u64>()
+        );
+    }
+}
 
-    let mut covered_points: HashMap<Point, u32> = HashMap::new();
+fn part1<const N: usize>(input: &[Line<N>]) -> usize {
+    count_overlaps_with(input, Mode::Orthogonal)
+}
 
-    for line in non_diagonal {
-        let points = line.get_points();
+fn part2<const N: usize>(input: &[Line<N>]) -> usize {
+    count_overlaps_with(input, Mode::Diagonal)
+}
 
-        for point in points {
-            *covered_points.entry(point).or_insert(0) += 1;
+// Which lines take part in counting overlaps. The puzzle only has lines along an axis or at exactly 45°, but
+// any slope works.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Orthogonal,
+    Diagonal,
+    AnySlope,
+}
+
+impl Mode {
+    pub fn includes<const N: usize>(&self, line: &Line<N>) -> bool {
+        match self {
+            Mode::Orthogonal => line.is_axis_aligned(),
+            Mode::Diagonal => line.is_axis_aligned() || line.is_diagonal(),
+            Mode::AnySlope => true,
         }
     }
+}
 
-    covered_points
+fn count_overlaps_with<const N: usize>(input: &[Line<N>], mode: Mode) -> usize {
+    let lines = input
+        .iter()
+        .filter(|line| mode.includes(line))
+        .cloned()
+        .collect::<Vec<Line<N>>>();
+
+    count_overlaps(&lines)
+}
+
+// Counts the lattice points covered by at least two lines, without visiting the points themselves.
+//
+// Every line lies on a carrier: the infinite line through it, which we key by its reduced direction and a
+// base point. Lines sharing a carrier can only overlap along it, so along each carrier we sweep over the lines'
+// endpoints to find the stretches covered twice and add up their lengths. Lines on different carriers meet
+// in at most a single point, so those are found pairwise. A crossing point might already sit in an overlapping
+// stretch, or in several on different carriers, so the total is corrected for each of those.
+fn count_overlaps<const N: usize>(lines: &[Line<N>]) -> usize {
+    let mut carriers: HashMap<Carrier<N>, Vec<(i64, i64)>> = HashMap::new();
+    for line in lines {
+        let (carrier, start, end) = Carrier::of(line);
+        carriers.entry(carrier).or_default().push((start, end));
+    }
+
+    let overlaps = carriers
         .into_iter()
-        .filter(|(_, count)| *count > 1)
-        .count()
+        .map(|(carrier, spans)| (carrier, overlapping_spans(spans)))
+        .filter(|(_, spans)| !spans.is_empty())
+        .collect::<HashMap<Carrier<N>, Vec<(i64, i64)>>>();
+
+    let mut count = overlaps
+        .values()
+        .flatten()
+        .map(|(start, end)| (end - start + 1) as usize)
+        .sum::<usize>();
+
+    let mut crossings = HashSet::new();
+    for (i, a) in lines.iter().enumerate() {
+        for b in &lines[i + 1..] {
+            if let Intersection::Point(point) = a.intersection(b) {
+                crossings.insert(point);
+            }
+        }
+    }
+
+    let directions = overlaps
+        .keys()
+        .map(|carrier| carrier.direction)
+        .collect::<HashSet<[i64; N]>>();
+
+    for point in crossings {
+        let covering = directions
+            .iter()
+            .filter(|direction| {
+                let (carrier, position) = Carrier::through(&point, **direction);
+                overlaps.get(&carrier).is_some_and(|spans| {
+                    let idx = spans.partition_point(|(_, end)| *end < position);
+                    spans.get(idx).is_some_and(|(start, _)| *start <= position)
+                })
+            })
+            .count();
+
+        // Points in no overlapping stretch are new, those in several were counted once per stretch.
+        if covering == 0 {
+            count += 1;
+        } else {
+            count -= covering - 1;
+        }
+    }
+
+    count
+}
+
+// Sweeps over the spans' endpoints, returning the sorted, disjoint spans covered at least twice.
+fn overlapping_spans(spans: Vec<(i64, i64)>) -> Vec<(i64, i64)> {
+    let mut events = spans
+        .iter()
+        .flat_map(|(start, end)| vec![(*start, 1), (*end + 1, -1)])
+        .collect::<Vec<(i64, i32)>>();
+    events.sort_unstable();
+
+    let mut overlaps: Vec<(i64, i64)> = Vec::new();
+    let mut depth = 0;
+    let mut overlap_start = None;
+
+    for (position, change) in events {
+        depth += change;
+
+        match overlap_start {
+            None if depth > 1 => overlap_start = Some(position),
+            Some(start) if depth < 2 => {
+                // Closing and reopening on the same position continues the previous overlap.
+                match overlaps.last_mut() {
+                    Some(last) if last.1 + 1 == start => last.1 = position - 1,
+                    _ => overlaps.push((start, position - 1)),
+                }
+                overlap_start = None;
+            }
+            _ => {}
+        }
+    }
+
+    overlaps.retain(|(start, end)| start <= end);
+    overlaps
+}
+
+// Identifies the infinite line a `Line` lies on. Lattice points along it are numbered consecutively, so a
+// `Line` covers a span of positions on its carrier.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+struct Carrier<const N: usize> {
+    // The smallest lattice step along the line, with its first non-zero coordinate positive.
+    pub direction: [i64; N],
+    // The lattice point on the line numbered zero, which is the same for every point on the line.
+    pub base: [i64; N],
 }
 
-fn part2(input: &Vec<Line>) -> usize {
-    let mut covered_points: HashMap<Point, u32> = HashMap::new();
+impl<const N: usize> Carrier<N> {
+    pub fn of(line: &Line<N>) -> (Carrier<N>, i64, i64) {
+        // A single point is treated as a line of no length along the first axis.
+        let direction = line.direction().unwrap_or_else(|| {
+            let mut direction = [0; N];
+            direction[0] = 1;
+            direction
+        });
+        let (carrier, start) = Carrier::through(&line.start, direction);
+        let (_, end) = Carrier::through(&line.end, direction);
+
+        (carrier, start.min(end), start.max(end))
+    }
+
+    // The carrier with the given direction passing through the point, and the point's position along it.
+    pub fn through(point: &Point<N>, direction: [i64; N]) -> (Carrier<N>, i64) {
+        // Moving one step along the carrier changes the dot product with the direction by its squared length,
+        // so dividing numbers the lattice points on it consecutively.
+        // Reduced directions can be large, so the products are taken in i128.
+        let position =
+            dot(&point.coords, &direction).div_euclid(dot(&direction, &direction)) as i64;
+
+        let mut base = point.coords;
+        for (coord, step) in base.iter_mut().zip(direction.iter()) {
+            *coord -= position * step;
+        }
 
-    for line in input {
-        let points = line.get_points();
+        (Carrier { direction, base }, position)
+    }
+}
 
-        for point in points {
+fn dot<const N: usize>(a: &[i64; N], b: &[i64; N]) -> i128 {
+    a.iter()
+        .zip(b.iter())
+        .map(|(a, b)| *a as i128 * *b as i128)
+        .sum()
+}
+
+// Counts overlaps by listing every point of every line, which `count_overlaps` is checked against.
+#[cfg(test)]
+fn count_overlaps_by_rasterising<const N: usize>(lines: &[Line<N>]) -> usize {
+    let mut covered_points: HashMap<Point<N>, u32> = HashMap::new();
+
+    for line in lines {
+        for point in line.lattice_points() {
             *covered_points.entry(point).or_insert(0) += 1;
         }
     }
@@ -53,80 +294,341 @@ fn part2(input: &Vec<Line>) -> usize {
         .count()
 }
 
-fn get_input() -> anyhow::Result<Vec<Line>> {
-    const PATH: &str = "src/day05/input.txt";
+// Vents in the plane, as in the puzzle, or in space.
+enum Vents {
+    Planar(Vec<Line<2>>),
+    Spatial(Vec<Line<3>>),
+}
 
-    let file = File::open(PATH)?;
-    let reader = BufReader::new(file);
+fn get_input(path: Option<&str>) -> anyhow::Result<Vents> {
+    manifest::open("day05", path, parse_vents)
+}
 
-    reader
-        .lines()
-        .map(|line| {
-            let line = line.unwrap();
-            let mut parts = line.split(" -> ");
+fn parse_vents(data: &str) -> anyhow::Result<Vents> {
+    let input = data.lines().map(str::to_string).collect::<Vec<String>>();
 
-            Ok(Line {
-                start: Point::from_str(parts.next().unwrap())?,
-                end: Point::from_str(parts.next().unwrap())?,
-            })
+    match dimensions(&input)? {
+        2 => Ok(Vents::Planar(parse_lines(&input)?)),
+        3 => Ok(Vents::Spatial(parse_lines(&input)?)),
+        n => Err(anyhow::anyhow!(
+            "Vents in {} dimensions are not supported",
+            n
+        )),
+    }
+}
+
+// How many coordinates the vents have, going by the first line. An empty input is taken to be the 2D puzzle.
+fn dimensions(input: &[String]) -> anyhow::Result<usize> {
+    match input.iter().find(|line| !line.trim().is_empty()) {
+        Some(line) => {
+            let (start, _) = line
+                .split_once("->")
+                .with_context(|| format!("Expected 'start -> end' on line 1, found '{}'", line))?;
+            Ok(start.split(',').count())
+        }
+        None => Ok(2),
+    }
+}
+
+fn parse_lines<const N: usize>(input: &[String]) -> anyhow::Result<Vec<Line<N>>> {
+    input
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            Line::from_str(line).with_context(|| format!("Invalid vent on line {}", i + 1))
         })
-        .collect::<Result<Vec<Line>, _>>()
+        .collect()
 }
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
-struct Point {
-    pub x: i64,
-    pub y: i64,
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+struct Point<const N: usize> {
+    pub coords: [i64; N],
 }
 
-impl FromStr for Point {
+impl<const N: usize> FromStr for Point<N> {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut parts = s.split(",");
-        let x = i64::from_str(parts.next().unwrap())?;
-        let y = i64::from_str(parts.next().unwrap())?;
+        let parts = s.split(',').map(str::trim).collect::<Vec<&str>>();
+        if parts.len() != N {
+            return Err(anyhow::anyhow!(
+                "Expected {} coordinates, found {} in '{}'",
+                N,
+                parts.len(),
+                s.trim()
+            ));
+        }
 
-        Ok(Point { x, y })
+        let mut coords = [0; N];
+        for (coord, part) in coords.iter_mut().zip(parts) {
+            *coord = i64::from_str(part)?;
+        }
+
+        Ok(Point { coords })
     }
 }
 
-#[derive(Debug, Clone)]
-struct Line {
-    pub start: Point,
-    pub end: Point,
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Line<const N: usize> {
+    pub start: Point<N>,
+    pub end: Point<N>,
+}
+
+impl<const N: usize> FromStr for Line<N> {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s
+            .split_once("->")
+            .with_context(|| format!("Expected 'start -> end', found '{}'", s))?;
+
+        let line = Line {
+            start: Point::from_str(start)?,
+            end: Point::from_str(end)?,
+        };
+
+        // Everything along a line is measured from its start, so the span has to fit the coordinates' own type.
+        for (start, end) in line.start.coords.iter().zip(line.end.coords.iter()) {
+            if end.checked_sub(*start).is_none_or(|d| d == i64::MIN) {
+                return Err(anyhow::anyhow!(
+                    "The span from {} to {} is too wide, in '{}'",
+                    start,
+                    end,
+                    s.trim()
+                ));
+            }
+        }
+
+        Ok(line)
+    }
 }
 
-impl Line {
-    pub fn is_vertical(&self) -> bool {
-        self.start.x == self.end.x
+impl<const N: usize> Line<N> {
+    pub fn delta(&self) -> [i64; N] {
+        let mut delta = self.end.coords;
+        for (coord, start) in delta.iter_mut().zip(self.start.coords.iter()) {
+            *coord -= start;
+        }
+        delta
+    }
+
+    // At most one coordinate changes along the line.
+    pub fn is_axis_aligned(&self) -> bool {
+        self.delta().iter().filter(|d| **d != 0).count() <= 1
     }
 
-    pub fn is_horizontal(&self) -> bool {
-        self.start.y == self.end.y
+    // Every coordinate which changes along the line changes by the same amount, so each step moves by one in
+    // each of them.
+    pub fn is_diagonal(&self) -> bool {
+        let delta = self.delta();
+        let mut moving = delta.iter().filter(|d| **d != 0).map(|d| d.abs());
+        match moving.next() {
+            Some(first) => moving.all(|d| d == first),
+            None => false,
+        }
     }
 
-    pub fn get_points(&self) -> Vec<Point> {
-        let rise = self.end.y - self.start.y;
-        let run = self.end.x - self.start.x;
+    // The smallest lattice step from start towards end, normalised so its first non-zero coordinate is
+    // positive. None for a line of a single point.
+    pub fn direction(&self) -> Option<[i64; N]> {
+        let mut delta = self.delta();
+        let gcd = delta.iter().fold(0, |gcd, d| self::gcd(gcd, d.abs()));
+
+        if gcd == 0 {
+            return None;
+        }
+
+        let sign = delta.iter().find(|d| **d != 0).map_or(1, |d| d.signum());
+        for d in delta.iter_mut() {
+            *d = *d / gcd * sign;
+        }
+
+        Some(delta)
+    }
+
+    // The single lattice point where two lines on different carriers meet, if there is one.
+    pub fn crossing_point(&self, other: &Line<N>) -> Option<Point<N>> {
+        // Any crossing lies in both bounding boxes. Inside them the lines' starts are no further apart than the
+        // lines are long, which keeps the products below within i128 however large the coordinates are.
+        let disjoint = (0..N).any(|k| {
+            let (a, b) = (self.start.coords[k], self.end.coords[k]);
+            let (c, d) = (other.start.coords[k], other.end.coords[k]);
+            a.max(b) < c.min(d) || c.max(d) < a.min(b)
+        });
+        if disjoint {
+            return None;
+        }
+
+        let p = self.start.coords.map(i128::from);
+        let r = self.delta().map(i128::from);
+        let q = other.start.coords.map(i128::from);
+        let w = other.delta().map(i128::from);
+
+        // Solve start + s * r = other.start + u * w, for s and u in [0, 1], using any two coordinates in which
+        // the lines aren't parallel.
+        let pair = (0..N)
+            .flat_map(|i| (i + 1..N).map(move |j| (i, j)))
+            .find(|(i, j)| r[*i] * w[*j] - r[*j] * w[*i] != 0);
+
+        let (i, j) = match pair {
+            Some(pair) => pair,
+            // Parallel lines, or at least one is a single point.
+            None => {
+                return match (self.direction(), other.direction()) {
+                    (None, None) if self.start == other.start => Some(self.start),
+                    (None, Some(_)) if other.contains(&self.start) => Some(self.start),
+                    (Some(_), None) if self.contains(&other.start) => Some(other.start),
+                    _ => None,
+                }
+            }
+        };
+
+        let mut denom = r[i] * w[j] - r[j] * w[i];
+        let mut s_num = (q[i] - p[i]) * w[j] - (q[j] - p[j]) * w[i];
+        let mut u_num = (q[i] - p[i]) * r[j] - (q[j] - p[j]) * r[i];
+
+        if denom < 0 {
+            denom = -denom;
+            s_num = -s_num;
+            u_num = -u_num;
+        }
+
+        if s_num < 0 || s_num > denom || u_num < 0 || u_num > denom {
+            return None;
+        }
+
+        // With s and u in lowest terms, start + s * r is a lattice point exactly when s's denominator divides
+        // every coordinate of r, and the step taken is then no longer than the line.
+        let along = |start: &[i128; N], delta: &[i128; N], num: i128| {
+            let gcd = gcd_wide(num, denom);
+            let (num, denom) = (num / gcd, denom / gcd);
+
+            let mut coords = [0; N];
+            for k in 0..N {
+                if delta[k] % denom != 0 {
+                    return None;
+                }
+                coords[k] = start[k] + delta[k] / denom * num;
+            }
+            Some(coords)
+        };
+
+        // The lines might still miss each other in the other coordinates.
+        let coords = along(&p, &r, s_num)?;
+        if along(&q, &w, u_num)? != coords {
+            return None;
+        }
+
+        Some(Point {
+            coords: coords.map(|coord| coord as i64),
+        })
+    }
+
+    pub fn contains(&self, point: &Point<N>) -> bool {
+        let within = (0..N).all(|k| {
+            let (start, end) = (self.start.coords[k], self.end.coords[k]);
+            point.coords[k] >= start.min(end) && point.coords[k] <= start.max(end)
+        });
+        if !within {
+            return false;
+        }
+
+        // Inside the bounding box the offset from the start is no wider than the line.
+        let r = self.delta();
+        let mut offset = point.coords;
+        for (coord, start) in offset.iter_mut().zip(self.start.coords.iter()) {
+            *coord -= start;
+        }
+
+        (0..N).all(|i| {
+            (i + 1..N).all(|j| r[i] as i128 * offset[j] as i128 == r[j] as i128 * offset[i] as i128)
+        })
+    }
+
+    // Every lattice point exactly on the line, from start to end.
+    pub fn lattice_points(&self) -> impl Iterator<Item = Point<N>> {
+        let (start, steps) = (self.start, self.lattice_count() - 1);
 
         // Reduce slope as much as possible
-        let gcd = gcd(rise.abs(), run.abs());
-        let rise = rise / gcd;
-        let run = run / gcd;
+        let mut step = self.delta();
+        let gcd = step.iter().fold(0, |gcd, d| self::gcd(gcd, d.abs())).max(1);
+        for d in step.iter_mut() {
+            *d /= gcd;
+        }
 
-        let mut x = self.start.x;
-        let mut y = self.start.y;
-        let mut points = Vec::new();
+        (0..=steps as i64).map(move |n| {
+            let mut coords = start.coords;
+            for (coord, d) in coords.iter_mut().zip(step.iter()) {
+                *coord += n * d;
+            }
+            Point { coords }
+        })
+    }
 
-        while x != self.end.x || y != self.end.y {
-            points.push(Point { x, y });
-            x += run;
-            y += rise;
+    // Consecutive lattice points on a line are one reduced step apart, so there's one more point than there are
+    // steps.
+    pub fn lattice_count(&self) -> u64 {
+        self.delta()
+            .iter()
+            .fold(0, |gcd, d| self::gcd(gcd, d.abs())) as u64
+            + 1
+    }
+
+    pub fn intersection(&self, other: &Line<N>) -> Intersection<N> {
+        let (carrier, start, end) = Carrier::of(self);
+        let (other_carrier, other_start, other_end) = Carrier::of(other);
+
+        // Single points are only given a direction for carriers, they can still sit on any other line.
+        let collinear =
+            carrier == other_carrier && self.direction().is_some() && other.direction().is_some();
+
+        if !collinear {
+            return match self.crossing_point(other) {
+                Some(point) => Intersection::Point(point),
+                None => Intersection::None,
+            };
+        }
+
+        let (overlap_start, overlap_end) = (start.max(other_start), end.min(other_end));
+        if overlap_start > overlap_end {
+            return Intersection::None;
+        }
+
+        let at = |position: i64| {
+            let mut coords = carrier.base;
+            for (coord, d) in coords.iter_mut().zip(carrier.direction.iter()) {
+                *coord += position * d;
+            }
+            Point { coords }
+        };
+
+        if overlap_start == overlap_end {
+            Intersection::Point(at(overlap_start))
+        } else {
+            Intersection::Overlap(Line {
+                start: at(overlap_start),
+                end: at(overlap_end),
+            })
         }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Intersection<const N: usize> {
+    None,
+    // Lines crossing, or touching end to end, at a single lattice point.
+    Point(Point<N>),
+    // Collinear lines sharing a stretch, running in the carrier's direction.
+    Overlap(Line<N>),
+}
 
-        points.push(self.end.clone());
-        points
+// Euclid's algorithm, for the wider values met when solving for crossings. Always positive unless both are zero.
+fn gcd_wide(a: i128, b: i128) -> i128 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd_wide(b, a % b)
     }
 }
 
@@ -164,5 +666,332 @@ pub fn gcd(a: i64, b: i64) -> i64 {
         return gcd((a - b) / 2, b);
     }
 
-    return gcd((b - a) / 2, a);
+    gcd((b - a) / 2, a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::rng::Lcg;
+    use super::*;
+
+    fn line(x1: i64, y1: i64, x2: i64, y2: i64) -> Line<2> {
+        Line {
+            start: Point { coords: [x1, y1] },
+            end: Point { coords: [x2, y2] },
+        }
+    }
+
+    fn line3(start: [i64; 3], end: [i64; 3]) -> Line<3> {
+        Line {
+            start: Point { coords: start },
+            end: Point { coords: end },
+        }
+    }
+
+    #[test]
+    pub fn test_example() {
+        let lines = vec![
+            line(0, 9, 5, 9),
+            line(8, 0, 0, 8),
+            line(9, 4, 3, 4),
+            line(2, 2, 2, 1),
+            line(7, 0, 7, 4),
+            line(6, 4, 2, 0),
+            line(0, 9, 2, 9),
+            line(3, 4, 1, 4),
+            line(0, 0, 8, 8),
+            line(5, 5, 8, 2),
+        ];
+
+        assert_eq!(part1(&lines), 5);
+        assert_eq!(part2(&lines), 12);
+    }
+
+    #[test]
+    pub fn test_matches_rasterising() {
+        let mut rng = Lcg::new(0x2021);
+        let mut next = |bound: i64| rng.below(bound as u64) as i64;
+
+        for _ in 0..20 {
+            let lines = (0..60)
+                .map(|_| {
+                    let (x, y) = (next(30), next(30));
+                    let (dx, dy, len) = match next(5) {
+                        0 => (1, 0, next(15)),
+                        1 => (0, 1, next(15)),
+                        2 => (1, 1, next(15)),
+                        3 => (1, -1, next(15)),
+                        _ => (next(5) - 2, next(5) - 2, next(4)),
+                    };
+                    line(x, y, x + dx * len, y + dy * len)
+                })
+                .collect::<Vec<Line<2>>>();
+
+            assert_eq!(
+                count_overlaps(&lines),
+                count_overlaps_by_rasterising(&lines)
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_huge_coordinates() {
+        const N: i64 = 1_000_000_000_000;
+        let lines = vec![
+            line(0, 0, N, 0),
+            line(N / 2, 0, 2 * N, 0),
+            line(N / 4, -N, N / 4, N),
+            line(0, N, N, 0),
+            line(0, 0, N, N),
+        ];
+
+        // The shared stretch of the horizontal lines, plus five crossings outside of it. The anti-diagonal
+        // crossing both horizontal lines at (N, 0) is already part of the stretch.
+        assert_eq!(count_overlaps(&lines), (N / 2 + 1) as usize + 5);
+    }
+
+    #[test]
+    pub fn test_huge_crossings() {
+        const N: i64 = 1_000_000_000_000_000_000;
+        let lines = vec![
+            line(0, 0, N, N),
+            line(0, N, N, 0),
+            line(-N, -N, N - 1, N),
+            line(i64::MIN / 2, 0, i64::MAX / 2, 1),
+        ];
+
+        assert_eq!(
+            lines[0].intersection(&lines[1]),
+            Intersection::Point(Point {
+                coords: [N / 2, N / 2]
+            })
+        );
+        // These cross the diagonals between lattice points.
+        assert_eq!(lines[2].crossing_point(&lines[0]), None);
+        assert_eq!(lines[2].crossing_point(&lines[1]), None);
+        assert_eq!(lines[3].crossing_point(&lines[0]), None);
+
+        assert_eq!(part2(&lines[..2]), 1);
+        // Only their endpoints are lattice points, so the others share nothing else.
+        assert_eq!(count_overlaps_with(&lines, Mode::AnySlope), 1);
+    }
+
+    #[test]
+    pub fn test_large_directions() {
+        // The direction can't be reduced, so its squared length doesn't fit in 64 bits.
+        let sloped = line(0, 0, 3_000_000_000, 2_000_000_003);
+        let vertical = line(3_000_000_000, 0, 3_000_000_000, 2_000_000_003);
+
+        assert_eq!(
+            sloped.intersection(&vertical),
+            Intersection::Point(Point {
+                coords: [3_000_000_000, 2_000_000_003]
+            })
+        );
+        assert_eq!(count_overlaps(&[sloped.clone(), sloped, vertical]), 2);
+    }
+
+    #[test]
+    pub fn test_arbitrary_slopes() {
+        let lines = vec![
+            line(0, 0, 6, 3),
+            line(0, 3, 6, 0),
+            line(2, 1, 8, 4),
+            line(0, 1, 3, 2),
+        ];
+
+        assert_eq!(part2(&lines), 0);
+        // Only the first and third lines share points, (2, 1) to (6, 3). The others cross between lattice points.
+        assert_eq!(count_overlaps_with(&lines, Mode::AnySlope), 3);
+        assert_eq!(
+            count_overlaps_with(&lines, Mode::AnySlope),
+            count_overlaps_by_rasterising(&lines)
+        );
+    }
+
+    #[test]
+    pub fn test_lattice_points() {
+        let cases = vec![
+            (line(0, 0, 6, 3), vec![(0, 0), (2, 1), (4, 2), (6, 3)]),
+            (line(5, 5, 5, 5), vec![(5, 5)]),
+            (line(3, 7, 0, 1), vec![(3, 7), (2, 5), (1, 3), (0, 1)]),
+            (line(0, 0, 4, 3), vec![(0, 0), (4, 3)]),
+        ];
+
+        for (line, expected) in cases {
+            let points = line
+                .lattice_points()
+                .map(|p| (p.coords[0], p.coords[1]))
+                .collect::<Vec<(i64, i64)>>();
+            assert_eq!(points, expected);
+            assert_eq!(line.lattice_count(), expected.len() as u64);
+        }
+
+        assert_eq!(
+            line(0, 0, 3_000_000_000, 2_000_000_000).lattice_count(),
+            1_000_000_001
+        );
+    }
+
+    #[test]
+    pub fn test_intersections() {
+        let point = |x, y| Intersection::Point(Point { coords: [x, y] });
+
+        let cases = vec![
+            (line(0, 0, 4, 4), line(0, 4, 4, 0), point(2, 2)),
+            (line(0, 0, 6, 3), line(0, 3, 6, 0), Intersection::None),
+            (line(0, 0, 6, 3), line(4, 0, 4, 9), point(4, 2)),
+            (
+                line(0, 0, 6, 3),
+                line(8, 4, 2, 1),
+                Intersection::Overlap(line(2, 1, 6, 3)),
+            ),
+            (line(6, 3, 0, 0), line(6, 3, 10, 5), point(6, 3)),
+            (line(0, 0, 6, 3), line(8, 4, 10, 5), Intersection::None),
+            (line(0, 0, 6, 3), line(0, 1, 6, 4), Intersection::None),
+            (line(2, 1, 2, 1), line(0, 0, 6, 3), point(2, 1)),
+            (line(3, 1, 3, 1), line(0, 0, 6, 3), Intersection::None),
+        ];
+
+        for (a, b, expected) in cases {
+            assert_eq!(a.intersection(&b), expected, "{:?} & {:?}", a, b);
+            assert_eq!(b.intersection(&a), expected, "{:?} & {:?}", b, a);
+        }
+    }
+
+    #[test]
+    pub fn test_density() {
+        let lines = vec![line(0, 0, 2, 0), line(1, 0, 1, 2), line(0, 0, 2, 2)];
+        let density = Density::new(&lines).unwrap();
+        let stats = density.stats();
+
+        assert_eq!(stats.max_overlap, 2);
+        assert_eq!(
+            stats.histogram.into_iter().collect::<Vec<_>>(),
+            vec![(1, 3), (2, 3)]
+        );
+        assert_eq!(
+            stats.bounds,
+            Some((Point { coords: [0, 0] }, Point { coords: [2, 2] }))
+        );
+
+        let mut csv = Vec::new();
+        density.write_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "x,y,count\n0,0,2\n1,0,2\n2,0,1\n1,1,2\n1,2,1\n2,2,1\n"
+        );
+
+        let mut ppm = Vec::new();
+        density.write_ppm(&mut ppm).unwrap();
+        assert!(ppm.starts_with(b"P6\n3 3\n255\n"));
+        assert_eq!(ppm.len(), 11 + 3 * 3 * 3);
+    }
+
+    #[test]
+    pub fn test_example_in_3d() {
+        let input = "0,9,4 -> 5,9,4\n8,0,4 -> 0,8,4\n9,4,4 -> 3,4,4\n2,2,4 -> 2,1,4\n7,0,4 -> 7,4,4\n\
+                     6,4,4 -> 2,0,4\n0,9,4 -> 2,9,4\n3,4,4 -> 1,4,4\n0,0,4 -> 8,8,4\n5,5,4 -> 8,2,4"
+            .lines()
+            .map(String::from)
+            .collect::<Vec<String>>();
+
+        assert_eq!(dimensions(&input).unwrap(), 3);
+        let lines = parse_lines::<3>(&input).unwrap();
+        assert_eq!(part1(&lines), 5);
+        assert_eq!(part2(&lines), 12);
+    }
+
+    #[test]
+    pub fn test_parse_errors() {
+        let input = vec!["1,2,3 -> 4,5,6".to_string(), "1,2 -> 4,5,6".to_string()];
+        let error = parse_lines::<3>(&input).unwrap_err();
+        assert_eq!(
+            format!("{:#}", error),
+            "Invalid vent on line 2: Expected 3 coordinates, found 2 in '1,2'"
+        );
+
+        assert!(parse_lines::<2>(&["1,2 4,5".to_string()]).is_err());
+        assert_eq!(dimensions(&[]).unwrap(), 2);
+
+        let wide = vec!["-5,0 -> 9223372036854775807,0".to_string()];
+        assert_eq!(
+            format!("{:#}", parse_lines::<2>(&wide).unwrap_err()),
+            "Invalid vent on line 1: The span from -5 to 9223372036854775807 is too wide, in \
+             '-5,0 -> 9223372036854775807,0'"
+        );
+        assert!(parse_lines::<2>(&["0,0 -> 9223372036854775807,0".to_string()]).is_ok());
+    }
+
+    #[test]
+    pub fn test_3d_matches_rasterising() {
+        let mut rng = Lcg::new(0x0305);
+        let mut next = |bound: i64| rng.below(bound as u64) as i64;
+
+        for _ in 0..20 {
+            let lines = (0..60)
+                .map(|_| {
+                    let start = [next(8), next(8), next(8)];
+                    let (step, len) = match next(3) {
+                        0 => ([next(3) - 1, next(3) - 1, next(3) - 1], next(8)),
+                        _ => ([next(5) - 2, next(5) - 2, next(5) - 2], next(4)),
+                    };
+                    let end = [
+                        start[0] + step[0] * len,
+                        start[1] + step[1] * len,
+                        start[2] + step[2] * len,
+                    ];
+                    line3(start, end)
+                })
+                .collect::<Vec<Line<3>>>();
+
+            assert_eq!(
+                count_overlaps(&lines),
+                count_overlaps_by_rasterising(&lines)
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_3d_intersections() {
+        let point = |coords| Intersection::Point(Point { coords });
+
+        let cases = vec![
+            (
+                line3([0, 0, 0], [4, 4, 4]),
+                line3([0, 4, 0], [4, 0, 4]),
+                point([2, 2, 2]),
+            ),
+            // Skew lines cross over each other in x and y without meeting.
+            (
+                line3([0, 0, 0], [4, 4, 0]),
+                line3([0, 4, 1], [4, 0, 1]),
+                Intersection::None,
+            ),
+            (
+                line3([0, 0, 0], [6, 3, 9]),
+                line3([8, 4, 12], [2, 1, 3]),
+                Intersection::Overlap(line3([2, 1, 3], [6, 3, 9])),
+            ),
+            (
+                line3([2, 1, 3], [2, 1, 3]),
+                line3([0, 0, 0], [6, 3, 9]),
+                point([2, 1, 3]),
+            ),
+            (
+                line3([2, 1, 4], [2, 1, 4]),
+                line3([0, 0, 0], [6, 3, 9]),
+                Intersection::None,
+            ),
+        ];
+
+        for (a, b, expected) in cases {
+            assert_eq!(a.intersection(&b), expected, "{:?} & {:?}", a, b);
+            assert_eq!(b.intersection(&a), expected, "{:?} & {:?}", b, a);
+        }
+
+        assert!(line3([0, 0, 0], [3, 3, 3]).is_diagonal());
+        assert!(line3([0, 0, 0], [3, 0, 0]).is_axis_aligned());
+        assert!(!line3([0, 0, 0], [3, 3, 1]).is_diagonal());
+    }
 }
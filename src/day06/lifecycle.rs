@@ -0,0 +1,203 @@
+use super::matrix::{Arithmetic, Matrix};
+
+// Beyond this the transition matrix gets too slow to raise to a power.
+const MAX_STATES: usize = 512;
+
+// How lanternfish age and spawn. Each fish has a timer counting down one a day; when it passes zero the fish
+// spawns and its timer restarts a full interval later, while newborns wait an extra delay before their first
+// interval. Fish can optionally die after spawning a given number of times.
+//
+// The state is a count of fish for each timer value and, if fish die, for each number of spawns so far. Slot
+// `spawns * timers() + timer` holds those fish.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LifecycleModel {
+    pub spawn_interval: usize,
+    pub newborn_delay: usize,
+    pub lifespan: Option<usize>,
+    pub offspring: u64,
+}
+
+impl LifecycleModel {
+    pub const PUZZLE: LifecycleModel = LifecycleModel {
+        spawn_interval: 7,
+        newborn_delay: 2,
+        lifespan: None,
+        offspring: 1,
+    };
+
+    pub fn new(
+        spawn_interval: usize,
+        newborn_delay: usize,
+        lifespan: Option<usize>,
+        offspring: u64,
+    ) -> anyhow::Result<Self> {
+        if spawn_interval == 0 {
+            return Err(anyhow::anyhow!("Spawn interval must be at least 1 day"));
+        }
+        if lifespan == Some(0) {
+            return Err(anyhow::anyhow!("Lifespan must be at least 1 cycle"));
+        }
+
+        // The sizes come from the command line, so check they fit before `state_size` relies on them.
+        let states = spawn_interval
+            .checked_add(newborn_delay)
+            .and_then(|timers| timers.checked_mul(lifespan.unwrap_or(1)));
+        match states {
+            Some(states) if states <= MAX_STATES => {}
+            Some(states) => {
+                return Err(anyhow::anyhow!(
+                    "Model needs {} states, more than the {} supported",
+                    states,
+                    MAX_STATES
+                ))
+            }
+            None => {
+                return Err(anyhow::anyhow!(
+                    "Model needs more than the {} states supported",
+                    MAX_STATES
+                ))
+            }
+        }
+
+        Ok(LifecycleModel {
+            spawn_interval,
+            newborn_delay,
+            lifespan,
+            offspring,
+        })
+    }
+
+    // The number of distinct timer values, from zero up to a newborn's.
+    pub fn timers(&self) -> usize {
+        self.spawn_interval + self.newborn_delay
+    }
+
+    pub fn state_size(&self) -> usize {
+        self.timers() * self.lifespan.unwrap_or(1)
+    }
+
+    // Places fish, given as counts per timer value, in a state of their own which haven't spawned yet.
+    pub fn initial_state(&self, fish: &[u64]) -> anyhow::Result<Vec<u64>> {
+        if let Some(timer) = fish.iter().skip(self.timers()).position(|count| *count > 0) {
+            return Err(anyhow::anyhow!(
+                "Timer {} is out of range for a model with timers 0 to {}",
+                timer + self.timers(),
+                self.timers() - 1
+            ));
+        }
+
+        let mut state = vec![0; self.state_size()];
+        for (slot, count) in state.iter_mut().zip(fish) {
+            *slot = *count;
+        }
+
+        Ok(state)
+    }
+
+    // Every `(from, to, fish)` move a day makes: each fish in slot `from` becomes that many fish in slot `to`.
+    pub fn transitions(&self) -> Vec<(usize, usize, u64)> {
+        let timers = self.timers();
+        let mut transitions = Vec::new();
+
+        for spawns in 0..self.lifespan.unwrap_or(1) {
+            let base = spawns * timers;
+
+            for timer in 1..timers {
+                transitions.push((base + timer, base + timer - 1, 1));
+            }
+
+            // Without a lifespan, every fish stays in the first group of slots.
+            let spawned = match self.lifespan {
+                Some(lifespan) if spawns + 1 >= lifespan => None,
+                Some(_) => Some(base + timers),
+                None => Some(base),
+            };
+            if let Some(spawned) = spawned {
+                transitions.push((base, spawned + self.spawn_interval - 1, 1));
+            }
+            transitions.push((base, timers - 1, self.offspring));
+        }
+
+        transitions
+    }
+
+    // Advances the state by a day, or None if any count no longer fits.
+    pub fn checked_step(&self, state: &[u64]) -> Option<Vec<u64>> {
+        let mut next = vec![0u64; state.len()];
+        for (from, to, fish) in self.transitions() {
+            next[to] = next[to].checked_add(state[from].checked_mul(fish)?)?;
+        }
+
+        Some(next)
+    }
+
+    // The factor the population grows by each day in the long run, which is the dominant eigenvalue of the
+    // transition matrix.
+    //
+    // Rather than finding it from the matrix, we use the renewal equation: a newborn spawns `offspring` fish
+    // `timers()` days after it's born and every interval after that, until it dies. In a population growing by
+    // λ a day, each fish must on average be replaced by its own offspring once discounted by λ for every day
+    // until they're born, so λ solves 1 = Σ offspring · λ^-(timers + k · interval) over the spawns k. The sum
+    // falls as λ rises, so a bisection finds the one positive root.
+    pub fn growth_rate(&self) -> f64 {
+        let offspring = self.offspring as f64;
+        let (first, interval) = (self.timers() as f64, self.spawn_interval as f64);
+
+        let discounted = |rate: f64| match self.lifespan {
+            Some(lifespan) => (0..lifespan)
+                .map(|k| offspring * rate.powf(-(first + k as f64 * interval)))
+                .sum::<f64>(),
+            None => offspring * rate.powf(-first) / (1.0 - rate.powf(-interval)),
+        };
+
+        // Fish which never spawn keep the population steady if they never die, otherwise it dies out.
+        if self.offspring == 0 {
+            return if self.lifespan.is_none() { 1.0 } else { 0.0 };
+        }
+
+        let (mut low, mut high) = match self.lifespan {
+            // Each term is at most offspring / λ once λ ≥ 1.
+            Some(lifespan) => (0.0, (offspring * lifespan as f64).max(1.0)),
+            // Immortal fish mean growth, and by 1 + offspring the sum has dropped below one.
+            None => (1.0, 1.0 + offspring),
+        };
+
+        for _ in 0..200 {
+            let mid = (low + high) / 2.0;
+            if discounted(mid) > 1.0 {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+
+        (low + high) / 2.0
+    }
+
+    // Days for the population to double in the long run, None if it isn't growing.
+    pub fn doubling_time(&self) -> Option<f64> {
+        let rate = self.growth_rate();
+
+        if rate > 1.0 {
+            Some(2f64.ln() / rate.ln())
+        } else {
+            None
+        }
+    }
+
+    // One day as a matrix acting on the state.
+    pub fn transition_matrix<T: Clone>(&self, arithmetic: &dyn Arithmetic<T>) -> Matrix<T> {
+        let size = self.state_size();
+        let mut cells = vec![vec![0u64; size]; size];
+        for (from, to, fish) in self.transitions() {
+            cells[to][from] += fish;
+        }
+
+        Matrix {
+            cells: cells
+                .into_iter()
+                .map(|row| row.into_iter().map(|fish| arithmetic.count(fish)).collect())
+                .collect(),
+        }
+    }
+}
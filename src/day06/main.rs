@@ -1,49 +1,480 @@
-use std::{
-    fs::File,
-    io::{BufReader, Read},
-    str::FromStr,
-};
+use std::{fs::File, str::FromStr};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use bignum::BigUint;
+use flags::take_flag;
+use lifecycle::LifecycleModel;
+use matrix::{Arithmetic, Exact, Modulo};
+use series::TimeSeries;
+
+mod bignum;
+#[path = "../flags.rs"]
+mod flags;
+mod lifecycle;
+#[path = "../manifest.rs"]
+mod manifest;
+mod matrix;
+mod series;
+
+// Past this the exact numbers get too slow to multiply to be useful.
+const MAX_EXACT_BITS: f64 = 15_000.0;
 
 fn main() -> Result<()> {
-    let input = get_input()?;
+    let mut args = std::env::args().skip(1).collect::<Vec<String>>();
+    let path = take_flag(&mut args, "--input")?;
+    let days = take_flag(&mut args, "--days")?;
+    let modulus = take_flag(&mut args, "--modulo")?;
+    let series = take_flag(&mut args, "--series")?;
+    let model = get_model(&mut args)?;
+
+    let input = get_input(path.as_deref())?;
+
+    println!("Part 1 result: {}", part1(input)?);
+    println!("Part 2 result: {}", part2(input)?);
+
+    let days = days
+        .map(|days| u64::from_str(&days).with_context(|| format!("Invalid day count: {}", days)))
+        .transpose()?;
+
+    if args.iter().any(|arg| arg == "--growth") {
+        println!("Daily growth rate: {:.6}", model.growth_rate());
+        match model.doubling_time() {
+            Some(doubling_time) => println!("Doubling time: {:.2} days", doubling_time),
+            None => println!("Doubling time: never, the population isn't growing"),
+        }
+    }
+
+    if let Some(path) = series {
+        let series = TimeSeries::new(&model, &input, days.unwrap_or(256) as usize)?;
+        series.write_csv(File::create(&path)?)?;
+        println!("Wrote {} days to {}", series.days.len(), path);
+    }
+
+    if let Some(days) = days {
+        match modulus {
+            Some(modulus) => {
+                let modulus = u64::from_str(&modulus)
+                    .with_context(|| format!("Invalid modulus: {}", modulus))?;
+                println!(
+                    "Population after {} days mod {}: {}",
+                    days,
+                    modulus,
+                    population_modulo(&model, &input, days, modulus)?
+                );
+            }
+            None => println!(
+                "Population after {} days: {}",
+                days,
+                population_exact(&model, &input, days)?
+            ),
+        }
+    }
 
-    println!("Part 1 result: {}", part1(input.clone()));
-    println!("Part 2 result: {}", part2(input.clone()));
     Ok(())
 }
 
-fn part1(data: [u64; 9]) -> u64 {
-    advance_model(data, 80)
+// Builds a model from the `--interval`, `--delay`, `--lifespan` and `--offspring` flags, defaulting to the puzzle.
+fn get_model(args: &mut Vec<String>) -> Result<LifecycleModel> {
+    let puzzle = LifecycleModel::PUZZLE;
+    let mut parse = |flag: &str, default: u64| -> Result<u64> {
+        match take_flag(args, flag)? {
+            Some(value) => u64::from_str(&value)
+                .with_context(|| format!("Invalid value for {}: {}", flag, value)),
+            None => Ok(default),
+        }
+    };
+
+    let spawn_interval = parse("--interval", puzzle.spawn_interval as u64)? as usize;
+    let newborn_delay = parse("--delay", puzzle.newborn_delay as u64)? as usize;
+    let lifespan = match parse("--lifespan", 0)? {
+        0 => None,
+        cycles => Some(cycles as usize),
+    };
+    let offspring = parse("--offspring", puzzle.offspring)?;
+
+    LifecycleModel::new(spawn_interval, newborn_delay, lifespan, offspring)
+}
+
+fn part1(data: [u64; 9]) -> Result<u64> {
+    advance_model(&LifecycleModel::PUZZLE, data.to_vec(), 80)
 }
 
-fn part2(data: [u64; 9]) -> u64 {
-    advance_model(data, 256)
+fn part2(data: [u64; 9]) -> Result<u64> {
+    advance_model(&LifecycleModel::PUZZLE, data.to_vec(), 256)
 }
 
-fn advance_model(mut fish: [u64; 9], days: usize) -> u64 {
+// Histograms can hold enough fish to overflow, which `--days` can still count exactly.
+fn advance_model(model: &LifecycleModel, mut state: Vec<u64>, days: usize) -> Result<u64> {
+    let overflow = || {
+        anyhow::anyhow!(
+            "Population after {} days no longer fits in 64 bits, use --days {} to count it exactly",
+            days,
+            days
+        )
+    };
+
     for _ in 0..days {
-        fish.rotate_left(1);
-        fish[6] += fish[8];
+        state = model.checked_step(&state).ok_or_else(overflow)?;
     }
 
-    fish.iter().sum()
+    state
+        .iter()
+        .try_fold(0u64, |total, count| total.checked_add(*count))
+        .ok_or_else(overflow)
 }
 
-fn get_input() -> Result<[u64; 9]> {
-    const PATH: &str = "src/day06/input.txt";
+// Advances the model by raising the daily transition to the power of the number of days, in O(log days) steps.
+fn population<T: Clone>(
+    model: &LifecycleModel,
+    fish: &[u64],
+    days: u64,
+    arithmetic: &dyn Arithmetic<T>,
+) -> Result<T> {
+    let state = model
+        .initial_state(fish)?
+        .iter()
+        .map(|count| arithmetic.count(*count))
+        .collect::<Vec<T>>();
 
-    let file = File::open(PATH)?;
-    let mut reader = BufReader::new(file);
+    Ok(model
+        .transition_matrix(arithmetic)
+        .power(days, arithmetic)
+        .apply(&state, arithmetic)
+        .iter()
+        .fold(arithmetic.zero(), |sum, count| arithmetic.add(&sum, count)))
+}
 
-    let mut data = String::new();
-    reader.read_to_string(&mut data)?;
+fn population_exact(model: &LifecycleModel, fish: &[u64], days: u64) -> Result<BigUint> {
+    // Every fish turns into at most 1 + offspring fish each interval, which bounds how big the numbers get.
+    let bits =
+        (days / model.spawn_interval as u64 + 1) as f64 * (1.0 + model.offspring as f64).log2();
+    if bits > MAX_EXACT_BITS {
+        return Err(anyhow::anyhow!(
+            "Exact populations after {} days could need {:.0} bits, more than the {} supported, use a modulus",
+            days,
+            bits,
+            MAX_EXACT_BITS
+        ));
+    }
+
+    population(model, fish, days, &Exact)
+}
+
+fn population_modulo(model: &LifecycleModel, fish: &[u64], days: u64, modulus: u64) -> Result<u64> {
+    if modulus == 0 {
+        return Err(anyhow::anyhow!("Modulus must be at least 1"));
+    }
+
+    population(model, fish, days, &Modulo(modulus))
+}
+
+fn get_input(path: Option<&str>) -> Result<[u64; 9]> {
+    manifest::open("day06", path, parse_fish)
+}
+
+// Reads fish as counts per timer value. Either a list of timers, one per fish, or for large populations a
+// histogram of `timer:count` entries. Entries can be separated by commas, whitespace or newlines.
+fn parse_fish(data: &str) -> Result<[u64; 9]> {
+    let entries = data
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|entry| !entry.is_empty())
+        .collect::<Vec<&str>>();
+    let histogram = entries.iter().any(|entry| entry.contains(':'));
 
     let mut counts: [u64; 9] = [0; 9];
-    for elem in data.split(",") {
-        counts[usize::from_str(elem)?] += 1;
+    for (i, entry) in entries.into_iter().enumerate() {
+        let (timer, count) = match entry.split_once(':') {
+            Some((timer, count)) => (timer, count),
+            None if histogram => {
+                return Err(anyhow::anyhow!(
+                    "Expected 'timer:count' at position {}, found '{}'",
+                    i + 1,
+                    entry
+                ))
+            }
+            None => (entry, "1"),
+        };
+
+        let timer = usize::from_str(timer)
+            .with_context(|| format!("Invalid timer '{}' at position {}", timer, i + 1))?;
+        let count = u64::from_str(count)
+            .with_context(|| format!("Invalid count '{}' at position {}", count, i + 1))?;
+
+        if timer >= counts.len() {
+            return Err(anyhow::anyhow!(
+                "Timer {} at position {} is out of range, expected 0 to {}",
+                timer,
+                i + 1,
+                counts.len() - 1
+            ));
+        }
+
+        counts[timer] = counts[timer]
+            .checked_add(count)
+            .with_context(|| format!("Too many fish with timer {}", timer))?;
     }
 
     Ok(counts)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: [u64; 9] = [0, 1, 1, 2, 1, 0, 0, 0, 0];
+    const PUZZLE: LifecycleModel = LifecycleModel::PUZZLE;
+
+    #[test]
+    pub fn test_example() {
+        assert_eq!(part1(EXAMPLE).unwrap(), 5934);
+        assert_eq!(part2(EXAMPLE).unwrap(), 26984457539);
+    }
+
+    #[test]
+    pub fn test_overflowing_histogram() {
+        let fish = parse_fish("0:100000000000").unwrap();
+
+        assert!(part1(fish).is_ok());
+        assert_eq!(
+            part2(fish).unwrap_err().to_string(),
+            "Population after 256 days no longer fits in 64 bits, use --days 256 to count it exactly"
+        );
+
+        let exact = population_exact(&PUZZLE, &fish, 256).unwrap();
+        let single = population_exact(&PUZZLE, &[1], 256).unwrap();
+        assert_eq!(exact.to_string(), format!("{}00000000000", single));
+    }
+
+    #[test]
+    pub fn test_matches_iterating() {
+        const PRIME: u64 = 1_000_000_007;
+
+        for days in 0..=400 {
+            let expected = advance_model(&PUZZLE, EXAMPLE.to_vec(), days).unwrap();
+
+            assert_eq!(
+                population_exact(&PUZZLE, &EXAMPLE, days as u64)
+                    .unwrap()
+                    .to_string(),
+                expected.to_string()
+            );
+            assert_eq!(
+                population_modulo(&PUZZLE, &EXAMPLE, days as u64, PRIME).unwrap(),
+                expected % PRIME
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_huge_day_counts() {
+        const PRIME: u64 = 998_244_353;
+        let arithmetic = Modulo(PRIME);
+
+        // Advancing by a sum of days is the same as advancing by each in turn.
+        let whole = population(&PUZZLE, &EXAMPLE, 1_000_000_000_000, &arithmetic).unwrap();
+        let matrix = PUZZLE.transition_matrix(&arithmetic);
+        let split = matrix
+            .power(999_999_999_999, &arithmetic)
+            .multiply(&matrix, &arithmetic)
+            .apply(&EXAMPLE, &arithmetic)
+            .iter()
+            .fold(0, |sum, count| (sum + count) % PRIME);
+        assert_eq!(whole, split);
+
+        assert!(population_exact(&PUZZLE, &EXAMPLE, 1_000_000).is_err());
+        assert!(population_modulo(&PUZZLE, &EXAMPLE, 10, 0).is_err());
+        assert_eq!(population_modulo(&PUZZLE, &EXAMPLE, 10, 1).unwrap(), 0);
+    }
+
+    #[test]
+    pub fn test_lifecycle_models() {
+        // Each fish spawns one newborn and dies the same day, so the population never changes.
+        let replacing = LifecycleModel::new(2, 0, Some(1), 1).unwrap();
+        assert_eq!(replacing.state_size(), 2);
+        assert_eq!(advance_model(&replacing, vec![3, 2], 50).unwrap(), 5);
+
+        // Twins every day, with the parent dying straight away.
+        let doubling = LifecycleModel::new(1, 0, Some(1), 2).unwrap();
+        assert_eq!(advance_model(&doubling, vec![1], 10).unwrap(), 1024);
+
+        // Fish live through three spawns, the puzzle would keep them all.
+        let mortal = LifecycleModel::new(7, 2, Some(3), 1).unwrap();
+        assert_eq!(mortal.state_size(), 27);
+        assert!(
+            advance_model(&mortal, mortal.initial_state(&EXAMPLE).unwrap(), 80).unwrap()
+                < part1(EXAMPLE).unwrap()
+        );
+
+        assert!(LifecycleModel::new(0, 2, None, 1).is_err());
+        assert!(LifecycleModel::new(7, 2, Some(0), 1).is_err());
+        assert!(LifecycleModel::new(300, 300, None, 1).is_err());
+        assert!(LifecycleModel::new(usize::MAX, 2, None, 1).is_err());
+        assert!(LifecycleModel::new(7, 2, Some(usize::MAX / 4), 1).is_err());
+        assert!(replacing.initial_state(&[0, 0, 1]).is_err());
+    }
+
+    #[test]
+    pub fn test_models_match_iterating() {
+        const PRIME: u64 = 1_000_000_007;
+
+        for interval in 1..5 {
+            for delay in 0..3 {
+                for lifespan in [None, Some(1), Some(2), Some(4)] {
+                    for offspring in 0..3 {
+                        let model =
+                            LifecycleModel::new(interval, delay, lifespan, offspring).unwrap();
+                        let fish = (0..model.timers() as u64).collect::<Vec<u64>>();
+
+                        for days in [0, 1, 2, 5, 13, 40] {
+                            let expected =
+                                advance_model(&model, model.initial_state(&fish).unwrap(), days)
+                                    .unwrap();
+
+                            assert_eq!(
+                                population_exact(&model, &fish, days as u64)
+                                    .unwrap()
+                                    .to_string(),
+                                expected.to_string(),
+                                "{:?} after {} days",
+                                model,
+                                days
+                            );
+                            assert_eq!(
+                                population_modulo(&model, &fish, days as u64, PRIME).unwrap(),
+                                expected % PRIME
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_time_series() {
+        let series = TimeSeries::new(&PUZZLE, &EXAMPLE, 18).unwrap();
+        assert_eq!(series.days.len(), 19);
+        assert_eq!(series.days[0].counts, EXAMPLE.to_vec());
+        assert_eq!(series.days[18].total, 26);
+        for (day, row) in series.days.iter().enumerate() {
+            assert_eq!(
+                row.total,
+                advance_model(&PUZZLE, EXAMPLE.to_vec(), day).unwrap()
+            );
+        }
+
+        let mut csv = Vec::new();
+        TimeSeries::new(&PUZZLE, &EXAMPLE, 1)
+            .unwrap()
+            .write_csv(&mut csv)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "day,timer_0,timer_1,timer_2,timer_3,timer_4,timer_5,timer_6,timer_7,timer_8,total\n\
+             0,0,1,1,2,1,0,0,0,0,5\n\
+             1,1,1,2,1,0,0,0,0,0,5\n"
+        );
+
+        // Mortal fish are counted by timer, whatever their age.
+        let mortal = LifecycleModel::new(2, 1, Some(2), 1).unwrap();
+        let series = TimeSeries::new(&mortal, &[1, 0, 0], 3).unwrap();
+        assert_eq!(series.days[1].counts, vec![0, 1, 1]);
+
+        let error = TimeSeries::new(&PUZZLE, &EXAMPLE, 1000).err().unwrap();
+        assert!(error.to_string().contains("on day"));
+    }
+
+    #[test]
+    pub fn test_growth_rate() {
+        // The largest root of λ⁹ = λ² + 1, the characteristic polynomial of the puzzle's matrix.
+        let rate = PUZZLE.growth_rate();
+        assert!((rate.powi(9) - rate.powi(2) - 1.0).abs() < 1e-9);
+        assert!((rate - 1.091_024).abs() < 1e-6);
+        assert!((PUZZLE.doubling_time().unwrap() - 7.9565).abs() < 1e-3);
+
+        // Averaged over long enough, daily growth settles on the rate, for models with mortality too.
+        for model in [
+            PUZZLE,
+            LifecycleModel::new(3, 1, Some(2), 1).unwrap(),
+            LifecycleModel::new(5, 2, Some(3), 2).unwrap(),
+            LifecycleModel::new(4, 0, Some(1), 1).unwrap(),
+        ] {
+            let series = TimeSeries::new(&model, &[1, 1, 1], 200).unwrap();
+            let ratio = (series.days[200].total as f64 / series.days[100].total as f64).powf(0.01);
+            assert!(
+                (ratio - model.growth_rate()).abs() < 1e-3,
+                "{:?}: {} vs {}",
+                model,
+                ratio,
+                model.growth_rate()
+            );
+        }
+
+        // Halving each generation, and a population that only holds steady.
+        assert!(LifecycleModel::new(3, 1, Some(1), 0)
+            .unwrap()
+            .doubling_time()
+            .is_none());
+        assert_eq!(
+            LifecycleModel::new(3, 1, None, 0).unwrap().growth_rate(),
+            1.0
+        );
+        assert!((LifecycleModel::new(4, 0, Some(1), 1).unwrap().growth_rate() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    pub fn test_parse_fish() {
+        assert_eq!(parse_fish("3,4,3,1,2").unwrap(), EXAMPLE);
+        assert_eq!(parse_fish("3,4,3,1,2\n").unwrap(), EXAMPLE);
+        assert_eq!(parse_fish(" 3, 4,3 ,\n1,\r\n2 ").unwrap(), EXAMPLE);
+        assert_eq!(parse_fish("1:1 2:1\n3:2,4:1").unwrap(), EXAMPLE);
+        assert_eq!(parse_fish("3:2,3:3").unwrap()[3], 5);
+        assert_eq!(parse_fish("").unwrap(), [0; 9]);
+
+        let error = |data| format!("{:#}", parse_fish(data).unwrap_err());
+        assert_eq!(
+            error("3,4,9,1"),
+            "Timer 9 at position 3 is out of range, expected 0 to 8"
+        );
+        assert!(error("3,x,1").starts_with("Invalid timer 'x' at position 2"));
+        assert!(error("3,-1").starts_with("Invalid timer '-1' at position 2"));
+        assert!(error("1:5,2:y").starts_with("Invalid count 'y' at position 2"));
+        assert_eq!(
+            error("1:5,2"),
+            "Expected 'timer:count' at position 2, found '2'"
+        );
+        assert_eq!(
+            error("1:18446744073709551615,1:1"),
+            "Too many fish with timer 1"
+        );
+    }
+
+    #[test]
+    pub fn test_input_files() {
+        let path = std::env::temp_dir().join(format!("day06-{}.txt", std::process::id()));
+        std::fs::write(&path, "1:1 2:1\n3:2,4:1\n").unwrap();
+        let fish = manifest::open("day06", path.to_str(), parse_fish);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(fish.unwrap(), EXAMPLE);
+
+        assert_eq!(get_input(None).unwrap().iter().sum::<u64>(), 300);
+        assert_eq!(
+            get_input(Some("src/day07/input.txt"))
+                .unwrap_err()
+                .to_string(),
+            "src/day07/input.txt is the input for day07, not day06"
+        );
+    }
+
+    #[test]
+    pub fn test_big_numbers() {
+        let a = BigUint::from(u64::MAX);
+        assert_eq!(a.add(&BigUint::from(1)).to_string(), "18446744073709551616");
+        assert_eq!(
+            a.mul(&a).to_string(),
+            "340282366920938463426481119284349108225"
+        );
+        assert_eq!(BigUint::zero().to_string(), "0");
+        assert_eq!(BigUint::from(1_000_000_000).to_string(), "1000000000");
+    }
+}
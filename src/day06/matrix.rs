@@ -0,0 +1,139 @@
+use super::bignum::BigUint;
+
+// The numbers a matrix can hold, so the same exponentiation gives both exact and modular results.
+pub trait Arithmetic<T> {
+    fn zero(&self) -> T;
+    fn one(&self) -> T;
+    fn count(&self, count: u64) -> T;
+    fn add(&self, a: &T, b: &T) -> T;
+    fn mul(&self, a: &T, b: &T) -> T;
+}
+
+pub struct Exact;
+
+impl Arithmetic<BigUint> for Exact {
+    fn zero(&self) -> BigUint {
+        BigUint::zero()
+    }
+
+    fn one(&self) -> BigUint {
+        BigUint::from(1)
+    }
+
+    fn count(&self, count: u64) -> BigUint {
+        BigUint::from(count)
+    }
+
+    fn add(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        a.add(b)
+    }
+
+    fn mul(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        a.mul(b)
+    }
+}
+
+// Arithmetic modulo the wrapped number.
+pub struct Modulo(pub u64);
+
+impl Arithmetic<u64> for Modulo {
+    fn zero(&self) -> u64 {
+        0
+    }
+
+    fn one(&self) -> u64 {
+        1 % self.0
+    }
+
+    fn count(&self, count: u64) -> u64 {
+        count % self.0
+    }
+
+    fn add(&self, a: &u64, b: &u64) -> u64 {
+        ((*a as u128 + *b as u128) % self.0 as u128) as u64
+    }
+
+    fn mul(&self, a: &u64, b: &u64) -> u64 {
+        ((*a as u128 * *b as u128) % self.0 as u128) as u64
+    }
+}
+
+// A square matrix, indexed by row then column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Matrix<T> {
+    pub cells: Vec<Vec<T>>,
+}
+
+impl<T: Clone> Matrix<T> {
+    pub fn identity(size: usize, arithmetic: &dyn Arithmetic<T>) -> Self {
+        let cells = (0..size)
+            .map(|row| {
+                (0..size)
+                    .map(|column| {
+                        if row == column {
+                            arithmetic.one()
+                        } else {
+                            arithmetic.zero()
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Matrix { cells }
+    }
+
+    pub fn size(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn multiply(&self, other: &Matrix<T>, arithmetic: &dyn Arithmetic<T>) -> Matrix<T> {
+        let size = self.size();
+        let cells = (0..size)
+            .map(|row| {
+                (0..size)
+                    .map(|column| {
+                        (0..size).fold(arithmetic.zero(), |sum, k| {
+                            let product =
+                                arithmetic.mul(&self.cells[row][k], &other.cells[k][column]);
+                            arithmetic.add(&sum, &product)
+                        })
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Matrix { cells }
+    }
+
+    pub fn apply(&self, vector: &[T], arithmetic: &dyn Arithmetic<T>) -> Vec<T> {
+        self.cells
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .zip(vector)
+                    .fold(arithmetic.zero(), |sum, (a, b)| {
+                        arithmetic.add(&sum, &arithmetic.mul(a, b))
+                    })
+            })
+            .collect()
+    }
+
+    // Square-and-multiply, so only O(log exponent) matrix products are needed.
+    pub fn power(&self, mut exponent: u64, arithmetic: &dyn Arithmetic<T>) -> Matrix<T> {
+        let mut result = Matrix::identity(self.size(), arithmetic);
+        let mut base = self.clone();
+
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result.multiply(&base, arithmetic);
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = base.multiply(&base, arithmetic);
+            }
+        }
+
+        result
+    }
+}
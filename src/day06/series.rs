@@ -0,0 +1,72 @@
+use std::io::{BufWriter, Write};
+
+use super::lifecycle::LifecycleModel;
+
+// The population on each day, starting with the initial fish on day 0.
+pub struct TimeSeries {
+    pub timers: usize,
+    pub days: Vec<Day>,
+}
+
+pub struct Day {
+    // Fish for each timer value, whatever number of times they've spawned.
+    pub counts: Vec<u64>,
+    pub total: u64,
+}
+
+impl TimeSeries {
+    pub fn new(model: &LifecycleModel, fish: &[u64], days: usize) -> anyhow::Result<Self> {
+        let mut state = model.initial_state(fish)?;
+        let mut series = TimeSeries {
+            timers: model.timers(),
+            days: Vec::with_capacity(days + 1),
+        };
+
+        let overflow =
+            |day: usize| anyhow::anyhow!("Population no longer fits in 64 bits on day {}", day);
+
+        for day in 0..=days {
+            if day > 0 {
+                state = model.checked_step(&state).ok_or_else(|| overflow(day))?;
+            }
+
+            let mut counts = vec![0u64; series.timers];
+            for (slot, count) in state.iter().enumerate() {
+                let timer = slot % series.timers;
+                counts[timer] = counts[timer]
+                    .checked_add(*count)
+                    .ok_or_else(|| overflow(day))?;
+            }
+            let total = counts
+                .iter()
+                .try_fold(0u64, |total, count| total.checked_add(*count))
+                .ok_or_else(|| overflow(day))?;
+
+            series.days.push(Day { counts, total });
+        }
+
+        Ok(series)
+    }
+
+    // One row per day, with a column for each timer value then the total.
+    pub fn write_csv<W: Write>(&self, writer: W) -> anyhow::Result<()> {
+        let mut writer = BufWriter::new(writer);
+
+        let timers = (0..self.timers)
+            .map(|timer| format!("timer_{}", timer))
+            .collect::<Vec<String>>();
+        writeln!(writer, "day,{},total", timers.join(","))?;
+
+        for (day, row) in self.days.iter().enumerate() {
+            let counts = row
+                .counts
+                .iter()
+                .map(|count| count.to_string())
+                .collect::<Vec<String>>();
+            writeln!(writer, "{},{},{}", day, counts.join(","), row.total)?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+use std::{fs, str::FromStr};
+
+use super::Crab;
+
+// The fuel a crab burns to move a given distance. This is wide enough to hold the square of any distance.
+pub trait CostFunction {
+    fn cost(&self, distance: u64) -> u128;
+
+    // Whether each extra step costs at least as much as the one before, which makes the total over all crabs
+    // convex in the target position.
+    fn is_convex(&self) -> bool {
+        false
+    }
+
+    // A few positions the best target is known to be among, if there's a closed form for it.
+    fn candidates(&self, _crabs: &[Crab]) -> Option<Vec<i64>> {
+        None
+    }
+
+    // The largest distance the cost is defined for.
+    fn max_distance(&self) -> Option<u64> {
+        None
+    }
+}
+
+// Each step costs one, so the best target is any weighted median.
+pub struct Linear;
+
+impl CostFunction for Linear {
+    fn cost(&self, distance: u64) -> u128 {
+        distance as u128
+    }
+
+    fn is_convex(&self) -> bool {
+        true
+    }
+
+    fn candidates(&self, crabs: &[Crab]) -> Option<Vec<i64>> {
+        let mut sorted = crabs.to_vec();
+        sorted.sort_unstable_by_key(|crab| crab.position);
+        let total = sorted.iter().map(|crab| crab.weight as u128).sum::<u128>();
+
+        // The lower median has at least half the weight at or before it, the upper more than half.
+        let mut seen = 0;
+        let (mut lower, mut upper) = (None, None);
+        for crab in &sorted {
+            seen += crab.weight as u128;
+            if lower.is_none() && seen * 2 >= total {
+                lower = Some(crab.position);
+            }
+            if upper.is_none() && seen * 2 > total {
+                upper = Some(crab.position);
+            }
+        }
+
+        Some(lower.into_iter().chain(upper).collect())
+    }
+}
+
+// Each step costs one more than the last. The total is within half a step of the mean, since its slope is the
+// sum of distances plus half the difference between the number of crabs either side.
+pub struct Triangular;
+
+impl CostFunction for Triangular {
+    fn cost(&self, distance: u64) -> u128 {
+        let distance = distance as u128;
+        distance * (distance + 1) / 2
+    }
+
+    fn is_convex(&self) -> bool {
+        true
+    }
+
+    fn candidates(&self, crabs: &[Crab]) -> Option<Vec<i64>> {
+        let (floor, ceil) = mean(crabs)?;
+        Some((floor.saturating_sub(1)..=ceil.saturating_add(1)).collect())
+    }
+}
+
+// The square of the distance, minimised at the mean.
+pub struct Quadratic;
+
+impl CostFunction for Quadratic {
+    fn cost(&self, distance: u64) -> u128 {
+        let distance = distance as u128;
+        distance * distance
+    }
+
+    fn is_convex(&self) -> bool {
+        true
+    }
+
+    fn candidates(&self, crabs: &[Crab]) -> Option<Vec<i64>> {
+        let (floor, ceil) = mean(crabs)?;
+        Some(vec![floor, ceil])
+    }
+}
+
+// Costs for each distance from zero, listed by the user.
+pub struct CostTable {
+    pub costs: Vec<u64>,
+}
+
+impl CostTable {
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let source = fs::read_to_string(path)?;
+        Self::parse(&source).map_err(|e| anyhow::anyhow!("Failed to load costs {}: {}", path, e))
+    }
+
+    // A comma or whitespace separated list, starting with the cost of not moving.
+    pub fn parse(source: &str) -> anyhow::Result<Self> {
+        let costs = source
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|entry| !entry.is_empty())
+            .enumerate()
+            .map(|(distance, entry)| {
+                u64::from_str(entry).map_err(|_| {
+                    anyhow::anyhow!("Invalid cost '{}' for distance {}", entry, distance)
+                })
+            })
+            .collect::<anyhow::Result<Vec<u64>>>()?;
+
+        if costs.is_empty() {
+            return Err(anyhow::anyhow!("Cost table is empty"));
+        }
+
+        Ok(CostTable { costs })
+    }
+}
+
+impl CostFunction for CostTable {
+    fn cost(&self, distance: u64) -> u128 {
+        self.costs[distance as usize] as u128
+    }
+
+    fn is_convex(&self) -> bool {
+        let steps = self
+            .costs
+            .windows(2)
+            .map(|pair| pair[1] as i128 - pair[0] as i128)
+            .collect::<Vec<i128>>();
+
+        steps.first().is_none_or(|first| *first >= 0)
+            && steps.windows(2).all(|pair| pair[0] <= pair[1])
+    }
+
+    fn max_distance(&self) -> Option<u64> {
+        Some(self.costs.len() as u64 - 1)
+    }
+}
+
+pub fn get_cost(name: &str) -> anyhow::Result<Box<dyn CostFunction>> {
+    match name {
+        "linear" => Ok(Box::new(Linear)),
+        "triangular" => Ok(Box::new(Triangular)),
+        "quadratic" => Ok(Box::new(Quadratic)),
+        _ => Err(anyhow::anyhow!(
+            "Unknown cost function: {} (expected one of: linear, triangular, quadratic)",
+            name
+        )),
+    }
+}
+
+// The floor and ceiling of the weighted mean position, None if the crabs are too heavy to sum.
+fn mean(crabs: &[Crab]) -> Option<(i64, i64)> {
+    let sum = crabs.iter().try_fold(0i128, |sum, crab| {
+        sum.checked_add((crab.position as i128).checked_mul(crab.weight as i128)?)
+    })?;
+    let count = crabs
+        .iter()
+        .try_fold(0i128, |count, crab| count.checked_add(crab.weight as i128))?;
+
+    let floor = sum.div_euclid(count) as i64;
+    if sum.rem_euclid(count) == 0 {
+        Some((floor, floor))
+    } else {
+        Some((floor, floor + 1))
+    }
+}
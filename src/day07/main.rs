@@ -1,63 +1,773 @@
-use std::{
-    fs::File,
-    io::{BufReader, Read},
-    str::FromStr,
-};
+use std::{convert::TryFrom, str::FromStr};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use cost::{get_cost, CostFunction, CostTable, Linear, Triangular};
+use flags::take_flag;
+
+mod cost;
+#[path = "../flags.rs"]
+mod flags;
+#[path = "../manifest.rs"]
+mod manifest;
+#[cfg(test)]
+#[path = "../rng.rs"]
+mod rng;
+
+// Scanning every target beyond this many positions takes too long.
+const MAX_SCAN: u64 = 100_000_000;
+// Aligning to several targets keeps a running total for every target and crab position, up to this many.
+const MAX_TABLE: u64 = 10_000_000;
 
 fn main() -> Result<()> {
-    let input = get_input()?;
-    println!("Part 1 result: {}", part1(input.clone()));
-    println!("Part 2 result: {}", part2(input));
+    let mut args = std::env::args().skip(1).collect::<Vec<String>>();
+    let path = take_flag(&mut args, "--input")?;
+    let cost = match (
+        take_flag(&mut args, "--cost")?,
+        take_flag(&mut args, "--cost-table")?,
+    ) {
+        (Some(_), Some(_)) => {
+            return Err(anyhow::anyhow!(
+                "Only one of --cost and --cost-table can be given"
+            ))
+        }
+        (Some(name), None) => Some(get_cost(&name)?),
+        (None, Some(path)) => Some(Box::new(CostTable::load(&path)?) as Box<dyn CostFunction>),
+        (None, None) => None,
+    };
+    let targets = take_flag(&mut args, "--targets")?
+        .map(|targets| {
+            usize::from_str(&targets)
+                .with_context(|| format!("Invalid number of targets: {}", targets))
+        })
+        .transpose()?;
+
+    let input = get_input(path.as_deref())?;
+    println!("Part 1 result: {}", part1(&input)?);
+    println!("Part 2 result: {}", part2(&input)?);
+
+    if let Some(targets) = targets {
+        let cost = cost.unwrap_or_else(|| Box::new(Linear));
+        let grouping = group(&input, targets, cost.as_ref())?;
+
+        println!(
+            "Best alignment to {} targets: fuel {}",
+            targets, grouping.cost
+        );
+        for group in grouping.groups {
+            println!(
+                "  crabs from {} to {} move to {}, fuel {}",
+                group.crabs.0, group.crabs.1, group.target, group.cost
+            );
+        }
+    } else if let Some(cost) = cost {
+        let alignment = align(&input, cost.as_ref())?;
+        let optimal = alignment
+            .optimal
+            .iter()
+            .map(|(start, end)| {
+                if start == end {
+                    start.to_string()
+                } else {
+                    format!("{} to {}", start, end)
+                }
+            })
+            .collect::<Vec<String>>();
+
+        println!(
+            "Best alignment: position {}, fuel {}",
+            optimal.join(", "),
+            alignment.cost
+        );
+
+        if args.iter().any(|arg| arg == "--breakdown") {
+            println!("Fuel per crab to reach {}:", alignment.position());
+            for (i, (crab, fuel)) in input.iter().zip(&alignment.breakdown).enumerate() {
+                println!(
+                    "  crab {} at {} (x{}): {}",
+                    i, crab.position, crab.weight, fuel
+                );
+            }
+        }
+    }
 
     Ok(())
 }
 
 // This part is effectively finding the L-1 norm of the dataset.
-fn part1(mut data: Vec<i64>) -> i64 {
-    data.sort();
-    let median = data[data.len() / 2];
+fn part1(data: &[Crab]) -> Result<u64> {
+    Ok(align(data, &Linear)?.cost)
+}
 
-    data.iter().map(|elem| (elem - median).abs()).sum()
+fn part2(data: &[Crab]) -> Result<u64> {
+    Ok(align(data, &Triangular)?.cost)
 }
 
-// Just brute force this one, couldn't find a nice closed form for this.
-fn part2(data: Vec<i64>) -> i64 {
-    let min = *data.iter().min().unwrap();
-    let max = *data.iter().max().unwrap();
+// A number of crabs sharing a position, which all move together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crab {
+    pub position: i64,
+    pub weight: u64,
+}
 
-    let calc_costs = |target: i64| {
-        data.iter()
-            .map(|elem| {
-                // The cost of each element is the sum of 1 to N where N = |elem - target|.
-                // This then reduces down as a linear sum.
-                let abs = (elem - target).abs();
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Alignment {
+    // Every target with the lowest cost, as inclusive runs of adjacent positions from left to right.
+    pub optimal: Vec<(i64, i64)>,
+    pub cost: u64,
+    // The fuel each entry's crabs burn together to reach the leftmost optimal target, in input order.
+    pub breakdown: Vec<u64>,
+}
 
-                abs * (abs + 1) / 2
-            })
-            .sum::<i64>()
+impl Alignment {
+    // The leftmost optimal target.
+    pub fn position(&self) -> i64 {
+        self.optimal[0].0
+    }
+}
+
+// How far apart two positions are. Crabs can be further apart than an i64 holds.
+fn distance(a: i64, b: i64) -> u64 {
+    (a as i128 - b as i128).unsigned_abs() as u64
+}
+
+// The middle of a non-empty range, rounding down or up. The range may be wider than an i64 holds.
+fn lower_middle(low: i64, high: i64) -> i64 {
+    (low as i128 + (distance(low, high) / 2) as i128) as i64
+}
+
+fn upper_middle(low: i64, high: i64) -> i64 {
+    (low as i128 + distance(low, high).div_ceil(2) as i128) as i64
+}
+
+fn too_large(target: i64) -> anyhow::Error {
+    anyhow::anyhow!("Fuel to reach {} is too large to count", target)
+}
+
+// The fuel one entry's crabs burn together to reach the target. Totals are kept exact while searching, since
+// far from the best target they can be much larger than the answer.
+fn fuel(crab: &Crab, target: i64, cost: &dyn CostFunction) -> Result<u128> {
+    (crab.weight as u128)
+        .checked_mul(cost.cost(distance(crab.position, target)))
+        .ok_or_else(|| too_large(target))
+}
+
+fn total_cost(crabs: &[Crab], target: i64, cost: &dyn CostFunction) -> Result<u128> {
+    crabs.iter().try_fold(0u128, |total, crab| {
+        total
+            .checked_add(fuel(crab, target, cost)?)
+            .ok_or_else(|| too_large(target))
+    })
+}
+
+fn narrow(fuel: u128, target: i64) -> Result<u64> {
+    u64::try_from(fuel)
+        .map_err(|_| anyhow::anyhow!("Fuel to reach {} no longer fits in 64 bits", target))
+}
+
+// The leftmost and rightmost crabs with any weight.
+fn extent(crabs: &[Crab]) -> Result<(i64, i64)> {
+    let mut positions = crabs
+        .iter()
+        .filter(|crab| crab.weight > 0)
+        .map(|crab| crab.position);
+    let first = positions.next().context("No crabs to align")?;
+
+    Ok(positions.fold((first, first), |(min, max), position| {
+        (min.min(position), max.max(position))
+    }))
+}
+
+// Finds the cheapest positions to move every crab to.
+//
+// With costs that never fall as distance grows, the best target is somewhere between the outermost crabs. If
+// the cost has a closed form we only try its candidates. Otherwise, a convex total falls then rises, so a
+// ternary search finds the bottom, done here as a binary search for the first step that isn't downhill. Any
+// other cost needs every position trying.
+fn align(crabs: &[Crab], cost: &dyn CostFunction) -> Result<Alignment> {
+    let (min, max) = extent(crabs)?;
+    let span = distance(min, max);
+
+    if let Some(max_distance) = cost.max_distance() {
+        if span > max_distance {
+            return Err(anyhow::anyhow!(
+                "Costs are only known up to distance {}, but crabs are up to {} apart",
+                max_distance,
+                span
+            ));
+        }
+    }
+
+    let total = |target: i64| total_cost(crabs, target, cost);
+
+    let optimal = if let Some(candidates) = cost.candidates(crabs) {
+        let totals = candidates
+            .into_iter()
+            .map(|position| position.clamp(min, max))
+            .map(|position| Ok((total(position)?, position)))
+            .collect::<Result<Vec<(u128, i64)>>>()?;
+        let (_, best) = totals.into_iter().min().unwrap();
+
+        vec![widen(best, (min, max), &total)?]
+    } else if cost.is_convex() {
+        let (mut low, mut high) = (min, max);
+        while low < high {
+            let mid = lower_middle(low, high);
+            if total(mid)? <= total(mid + 1)? {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+
+        vec![widen(low, (min, max), &total)?]
+    } else {
+        if span > MAX_SCAN {
+            return Err(anyhow::anyhow!(
+                "Crabs are {} apart, too far to try every position with a non-convex cost",
+                span
+            ));
+        }
+
+        let costs = (min..=max)
+            .map(|position| Ok((position, total(position)?)))
+            .collect::<Result<Vec<(i64, u128)>>>()?;
+        let lowest = costs.iter().map(|(_, cost)| *cost).min().unwrap();
+
+        let mut optimal: Vec<(i64, i64)> = Vec::new();
+        for (position, _) in costs.into_iter().filter(|(_, cost)| *cost == lowest) {
+            match optimal.last_mut() {
+                Some(last) if last.1 + 1 == position => last.1 = position,
+                _ => optimal.push((position, position)),
+            }
+        }
+
+        optimal
     };
 
-    let optimal = (min..max)
-        .map(|target| (target, calc_costs(target)))
-        .min_by(|x, y| x.1.cmp(&y.1))
-        .unwrap();
+    let target = optimal[0].0;
+    Ok(Alignment {
+        cost: narrow(total(target)?, target)?,
+        breakdown: crabs
+            .iter()
+            .map(|crab| narrow(fuel(crab, target, cost)?, target))
+            .collect::<Result<Vec<u64>>>()?,
+        optimal,
+    })
+}
+
+// A convex total's cheapest targets are all next to each other, so from one of them, search out either side
+// for the last positions costing the same.
+fn widen(
+    best: i64,
+    (min, max): (i64, i64),
+    total: &dyn Fn(i64) -> Result<u128>,
+) -> Result<(i64, i64)> {
+    let lowest = total(best)?;
+
+    let (mut low, mut high) = (min, best);
+    while low < high {
+        let mid = lower_middle(low, high);
+        if total(mid)? == lowest {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+    let start = low;
+
+    let (mut low, mut high) = (best, max);
+    while low < high {
+        let mid = upper_middle(low, high);
+        if total(mid)? == lowest {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+
+    Ok((start, low))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Grouping {
+    pub groups: Vec<Group>,
+    pub cost: u64,
+}
+
+// Crabs from one position to another, all moving to the same target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Group {
+    pub crabs: (i64, i64),
+    pub target: i64,
+    pub cost: u64,
+}
+
+// Splits the crabs between up to the given number of targets, for the least fuel overall.
+//
+// With costs that never fall as distance grows, each crab is best off at its nearest target, so the crabs
+// going to each target sit next to each other. That leaves choosing where to split the crabs, sorted by
+// position, which is done by dynamic programming over the cheapest way to send the first i crabs to k
+// targets. A running total of the fuel to each target over the sorted crabs gives the fuel for any group to
+// any target by subtraction, and the best target for a group is found by the same search as `align`.
+fn group(crabs: &[Crab], targets: usize, cost: &dyn CostFunction) -> Result<Grouping> {
+    if targets == 0 {
+        return Err(anyhow::anyhow!("Need at least one target to align to"));
+    }
+    if !cost.is_convex() {
+        return Err(anyhow::anyhow!(
+            "Aligning to several targets needs a convex cost"
+        ));
+    }
+
+    let (min, max) = extent(crabs)?;
+    let span = distance(min, max);
+    if cost
+        .max_distance()
+        .is_some_and(|max_distance| span > max_distance)
+    {
+        return Err(anyhow::anyhow!(
+            "Costs are only known up to distance {}, but crabs are up to {} apart",
+            cost.max_distance().unwrap(),
+            span
+        ));
+    }
+
+    // Crabs sharing a position always go to the same target.
+    let mut sorted: Vec<Crab> = Vec::new();
+    let mut weighted = crabs
+        .iter()
+        .filter(|crab| crab.weight > 0)
+        .copied()
+        .collect::<Vec<Crab>>();
+    weighted.sort_unstable_by_key(|crab| crab.position);
+    for crab in weighted {
+        match sorted.last_mut() {
+            Some(last) if last.position == crab.position => {
+                last.weight = last.weight.checked_add(crab.weight).with_context(|| {
+                    format!("Too many crabs at position {} to count", crab.position)
+                })?
+            }
+            _ => sorted.push(crab),
+        }
+    }
 
-    optimal.1
+    let n = sorted.len();
+    if span.saturating_add(1).saturating_mul(n as u64 + 1) > MAX_TABLE {
+        return Err(anyhow::anyhow!(
+            "{} crab positions spread over {} are too many to align to several targets",
+            n,
+            span as u128 + 1
+        ));
+    }
+
+    let running = (min..=max)
+        .map(|target| {
+            let mut total = 0u128;
+            std::iter::once(Ok(0))
+                .chain(sorted.iter().map(|crab| {
+                    total = total
+                        .checked_add(fuel(crab, target, cost)?)
+                        .ok_or_else(|| too_large(target))?;
+                    Ok(total)
+                }))
+                .collect::<Result<Vec<u128>>>()
+        })
+        .collect::<Result<Vec<Vec<u128>>>>()?;
+
+    // The cheapest target for the crabs from `first` up to `last` exclusive, which is between them.
+    let best_target = |first: usize, last: usize| {
+        let fuel = |target: i64| {
+            let running = &running[(target - min) as usize];
+            running[last] - running[first]
+        };
+
+        let (mut low, mut high) = (sorted[first].position, sorted[last - 1].position);
+        while low < high {
+            let mid = lower_middle(low, high);
+            if fuel(mid) <= fuel(mid + 1) {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+
+        (low, fuel(low))
+    };
+
+    // cheapest[k][i] is the least fuel for the first i crabs to reach k targets, with the crab starting the last
+    // group. Splits whose fuel is too large to count are left out, as they can't be the cheapest.
+    let targets = targets.min(n);
+    let mut cheapest = vec![vec![None; n + 1]; targets + 1];
+    cheapest[0][0] = Some((0u128, 0usize));
+    for k in 1..=targets {
+        for last in k..=n {
+            cheapest[k][last] = (k - 1..last)
+                .filter_map(|first| {
+                    let (before, _) = cheapest[k - 1][first]?;
+                    Some((before.checked_add(best_target(first, last).1)?, first))
+                })
+                .min();
+        }
+    }
+
+    let overflow = || anyhow::anyhow!("Fuel to reach the targets no longer fits in 64 bits");
+    let (cost, _) = cheapest[targets][n].ok_or_else(overflow)?;
+    let mut groups = Vec::new();
+    let (mut k, mut last) = (targets, n);
+    while k > 0 {
+        let (_, first) = cheapest[k][last].unwrap();
+        let (target, cost) = best_target(first, last);
+        groups.push(Group {
+            crabs: (sorted[first].position, sorted[last - 1].position),
+            target,
+            cost: narrow(cost, target)?,
+        });
+        k -= 1;
+        last = first;
+    }
+    groups.reverse();
+
+    Ok(Grouping {
+        groups,
+        cost: u64::try_from(cost).map_err(|_| overflow())?,
+    })
+}
+
+fn get_input(path: Option<&str>) -> Result<Vec<Crab>> {
+    manifest::open("day07", path, parse_crabs)
+}
+
+// Either a list of positions, one per crab, or `position:count` entries for several crabs at once. Entries can
+// be separated by commas, whitespace or newlines.
+fn parse_crabs(data: &str) -> Result<Vec<Crab>> {
+    data.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|entry| !entry.is_empty())
+        .enumerate()
+        .map(|(i, entry)| {
+            let (position, weight) = entry.split_once(':').unwrap_or((entry, "1"));
+
+            Ok(Crab {
+                position: i64::from_str(position).with_context(|| {
+                    format!("Invalid position '{}' at entry {}", position, i + 1)
+                })?,
+                weight: u64::from_str(weight)
+                    .with_context(|| format!("Invalid count '{}' at entry {}", weight, i + 1))?,
+            })
+        })
+        .collect()
 }
 
-fn get_input() -> Result<Vec<i64>> {
-    const PATH: &str = "src/day06/input.txt";
+#[cfg(test)]
+mod tests {
+    use super::cost::Quadratic;
+    use super::rng::Lcg;
+    use super::*;
+
+    const EXAMPLE: [i64; 10] = [16, 1, 2, 0, 4, 2, 7, 1, 2, 14];
+
+    fn crabs(positions: &[i64]) -> Vec<Crab> {
+        positions
+            .iter()
+            .map(|position| Crab {
+                position: *position,
+                weight: 1,
+            })
+            .collect()
+    }
+
+    // Tries every position, which `align` is checked against.
+    fn optimal_by_scanning(crabs: &[Crab], cost: &dyn CostFunction) -> (Vec<i64>, u64) {
+        let (min, max) = extent(crabs).unwrap();
+
+        let costs = (min..=max)
+            .map(|position| (position, total_cost(crabs, position, cost).unwrap() as u64))
+            .collect::<Vec<(i64, u64)>>();
+        let lowest = costs.iter().map(|(_, cost)| *cost).min().unwrap();
+
+        let optimal = costs
+            .into_iter()
+            .filter(|(_, cost)| *cost == lowest)
+            .map(|(position, _)| position)
+            .collect();
+
+        (optimal, lowest)
+    }
+
+    #[test]
+    pub fn test_example() {
+        let example = crabs(&EXAMPLE);
+        assert_eq!(part1(&example).unwrap(), 37);
+        assert_eq!(part2(&example).unwrap(), 168);
+        assert_eq!(align(&example, &Linear).unwrap().optimal, vec![(2, 2)]);
+
+        let alignment = align(&example, &Triangular).unwrap();
+        assert_eq!(alignment.optimal, vec![(5, 5)]);
+        assert_eq!(alignment.breakdown, vec![66, 10, 6, 15, 1, 6, 3, 10, 6, 45]);
+        assert_eq!(alignment.breakdown.iter().sum::<u64>(), alignment.cost);
+    }
+
+    #[test]
+    pub fn test_matches_scanning() {
+        let convex =
+            CostTable::parse("0 2 5 9 14 20 27 35 44 54 65 77 90 104 119 135 152").unwrap();
+        let bumpy = CostTable::parse("0 5 1 6 2 7 3 8 4 9 5 10 6 11 7 12 8").unwrap();
+        let flat = CostTable::parse("0 1 1 2 2 3 3 4 4 5 5 6 6 7 7 8 8").unwrap();
+        assert!(convex.is_convex());
+        assert!(!bumpy.is_convex());
+        assert!(!flat.is_convex());
+
+        let costs: Vec<&dyn CostFunction> =
+            vec![&Linear, &Triangular, &Quadratic, &convex, &bumpy, &flat];
+
+        let mut rng = Lcg::new(0x0707);
+
+        for _ in 0..200 {
+            // Some crabs share a position, some are weighted, and a few weigh nothing.
+            let mut crabs = (0..1 + rng.below(12))
+                .map(|_| Crab {
+                    position: rng.below(17) as i64 - 3,
+                    weight: [1, 1, 1, 2, 5, 0][rng.below(6) as usize],
+                })
+                .collect::<Vec<Crab>>();
+            crabs.push(Crab {
+                position: rng.below(17) as i64 - 3,
+                weight: 1 + rng.below(3),
+            });
+
+            for cost in &costs {
+                let (expected, lowest) = optimal_by_scanning(&crabs, *cost);
+                let alignment = align(&crabs, *cost).unwrap();
+
+                let optimal = alignment
+                    .optimal
+                    .iter()
+                    .flat_map(|(start, end)| *start..=*end)
+                    .collect::<Vec<i64>>();
+                assert_eq!(optimal, expected, "{:?}", crabs);
+                assert_eq!(alignment.cost, lowest);
+                assert_eq!(alignment.breakdown.iter().sum::<u64>(), lowest);
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_large_costs() {
+        // Squaring the distance to the best target no longer fits in 64 bits, though the total does.
+        let alignment = align(&crabs(&[0, 7_000_000_000, 7_000_000_000]), &Triangular).unwrap();
+        assert_eq!(alignment.optimal, vec![(4_666_666_667, 4_666_666_667)]);
+        assert_eq!(alignment.cost, 16_333_333_338_000_000_000);
+
+        assert_eq!(
+            align(&crabs(&[0, 10_000_000_000]), &Triangular)
+                .err()
+                .unwrap()
+                .to_string(),
+            "Fuel to reach 5000000000 no longer fits in 64 bits"
+        );
+        assert!(align(&crabs(&[0, 10_000_000_000]), &Quadratic).is_err());
+        assert!(group(&crabs(&[0, 10_000_000_000, 10_000_000_001]), 1, &Quadratic).is_err());
+    }
+
+    #[test]
+    pub fn test_distant_crabs() {
+        // Further apart than an i64 holds.
+        let distant = parse_crabs("-9000000000000000000,9000000000000000000").unwrap();
+        assert_eq!(part1(&distant).unwrap(), 18_000_000_000_000_000_000);
+        assert_eq!(
+            part2(&distant).unwrap_err().to_string(),
+            "Fuel to reach 0 no longer fits in 64 bits"
+        );
+        assert!(group(&distant, 2, &Linear).is_err());
+
+        let extreme = crabs(&[i64::MIN, i64::MAX]);
+        let alignment = align(&extreme, &Linear).unwrap();
+        assert_eq!(alignment.optimal, vec![(i64::MIN, i64::MAX)]);
+        assert_eq!(alignment.cost, u64::MAX);
+        assert!(align(&extreme, &Quadratic).is_err());
+    }
+
+    #[test]
+    pub fn test_ties() {
+        // Anywhere between the two middle crabs is as good as any other.
+        let alignment = align(
+            &crabs(&[0, 3, 1_000_000_000_000, 2_000_000_000_000]),
+            &Linear,
+        )
+        .unwrap();
+        assert_eq!(alignment.optimal, vec![(3, 1_000_000_000_000)]);
+        assert_eq!(alignment.position(), 3);
+        assert_eq!(
+            alignment.breakdown,
+            vec![3, 0, 999_999_999_997, 1_999_999_999_997]
+        );
+
+        let alignment = align(&crabs(&[0, 1]), &Triangular).unwrap();
+        assert_eq!(alignment.optimal, vec![(0, 1)]);
+
+        // Separate runs of positions when the cost isn't convex.
+        let alignment = align(&crabs(&[0, 4]), &CostTable::parse("0 9 1 9 2").unwrap()).unwrap();
+        assert_eq!(alignment.optimal, vec![(0, 0), (2, 2), (4, 4)]);
+    }
+
+    #[test]
+    pub fn test_weighted_crabs() {
+        let weighted = parse_crabs("16,1:2 2:3,0\n4, 7 14").unwrap();
+        assert_eq!(
+            weighted,
+            parse_crabs("16:1 1:2 2:3 0:1 4:1 7:1 14:1").unwrap()
+        );
+        assert_eq!(part1(&weighted).unwrap(), 37);
+        assert_eq!(part2(&weighted).unwrap(), 168);
+        assert_eq!(
+            align(&weighted, &Linear).unwrap().breakdown,
+            vec![14, 2, 0, 2, 2, 5, 12]
+        );
+
+        // A heavy enough crab pulls the median all the way to it.
+        let heavy = parse_crabs("0:10,5,6,7,8,9").unwrap();
+        assert_eq!(align(&heavy, &Linear).unwrap().optimal, vec![(0, 0)]);
+        let balanced = parse_crabs("0:5,5,6,7,8,9").unwrap();
+        assert_eq!(align(&balanced, &Linear).unwrap().optimal, vec![(0, 5)]);
+
+        assert!(format!("{:#}", parse_crabs("1,x").unwrap_err())
+            .starts_with("Invalid position 'x' at entry 2"));
+        assert!(format!("{:#}", parse_crabs("1:-2").unwrap_err())
+            .starts_with("Invalid count '-2' at entry 1"));
+        assert!(align(&parse_crabs("3:0").unwrap(), &Linear).is_err());
+    }
+
+    #[test]
+    pub fn test_heavy_crabs() {
+        // Moving the heavy crab at all would cost more than 64 bits hold, but the best targets are cheap.
+        let heavy = parse_crabs("0:100000000000000,1000000").unwrap();
+        let alignment = align(&heavy, &Linear).unwrap();
+        assert_eq!(alignment.optimal, vec![(0, 0)]);
+        assert_eq!(alignment.cost, 1_000_000);
+        assert_eq!(align(&heavy, &Triangular).unwrap().cost, 500_000_500_000);
+        assert_eq!(group(&heavy, 2, &Linear).unwrap().cost, 0);
+
+        let max = u64::MAX;
+        let tied = parse_crabs(&format!("0:{},1:{}", max, max)).unwrap();
+        assert_eq!(align(&tied, &Linear).unwrap().cost, max);
+
+        let apart = parse_crabs(&format!("0:{},2:{}", max, max)).unwrap();
+        assert_eq!(
+            align(&apart, &Linear).unwrap_err().to_string(),
+            "Fuel to reach 0 no longer fits in 64 bits"
+        );
+        assert!(group(&apart, 1, &Linear).is_err());
+
+        // Too heavy to take the mean of, so the best target is searched for instead.
+        let far = parse_crabs(&format!(
+            "9000000000000000000:{},9000000000000000000:{}",
+            max, max
+        ))
+        .unwrap();
+        assert_eq!(align(&far, &Quadratic).unwrap().cost, 0);
+
+        assert_eq!(
+            group(&parse_crabs(&format!("5:{},5:1", max)).unwrap(), 1, &Linear)
+                .unwrap_err()
+                .to_string(),
+            "Too many crabs at position 5 to count"
+        );
+    }
+
+    #[test]
+    pub fn test_grouping() {
+        let example = crabs(&EXAMPLE);
+
+        // One target is the same as aligning all the crabs.
+        for cost in [&Linear as &dyn CostFunction, &Triangular, &Quadratic] {
+            assert_eq!(
+                group(&example, 1, cost).unwrap().cost,
+                align(&example, cost).unwrap().cost
+            );
+        }
+
+        let grouping = group(&example, 2, &Linear).unwrap();
+        assert_eq!(
+            grouping.groups,
+            vec![
+                Group {
+                    crabs: (0, 7),
+                    target: 2,
+                    cost: 11
+                },
+                Group {
+                    crabs: (14, 16),
+                    target: 14,
+                    cost: 2
+                }
+            ]
+        );
+        assert_eq!(grouping.cost, 13);
+
+        // Every position can have its own target.
+        assert_eq!(group(&example, 7, &Linear).unwrap().cost, 0);
+        assert_eq!(group(&example, 100, &Linear).unwrap().groups.len(), 7);
+
+        assert!(group(&example, 0, &Linear).is_err());
+        assert!(group(
+            &example,
+            2,
+            &CostTable::parse("0 5 1 6 2 7 3 8 4 9 5 10 6 11 7 12 8").unwrap()
+        )
+        .is_err());
+    }
+
+    #[test]
+    pub fn test_grouping_matches_brute_force() {
+        let mut rng = Lcg::new(0x4907);
+
+        for _ in 0..50 {
+            let crabs = (0..1 + rng.below(10))
+                .map(|_| Crab {
+                    position: rng.below(20) as i64,
+                    weight: 1 + rng.below(3),
+                })
+                .collect::<Vec<Crab>>();
+            let (min, max) = extent(&crabs).unwrap();
+
+            for cost in [&Linear as &dyn CostFunction, &Triangular, &Quadratic] {
+                // Every pair of targets, with each crab going to the cheaper one.
+                let expected = (min..=max)
+                    .flat_map(|a| (a..=max).map(move |b| (a, b)))
+                    .map(|(a, b)| {
+                        crabs
+                            .iter()
+                            .map(|crab| {
+                                let to = |target: i64| {
+                                    crab.weight * cost.cost(distance(crab.position, target)) as u64
+                                };
+                                to(a).min(to(b))
+                            })
+                            .sum::<u64>()
+                    })
+                    .min()
+                    .unwrap();
 
-    let file = File::open(PATH)?;
-    let mut reader = BufReader::new(file);
+                let grouping = group(&crabs, 2, cost).unwrap();
+                assert_eq!(grouping.cost, expected, "{:?}", crabs);
+                assert_eq!(
+                    grouping.groups.iter().map(|group| group.cost).sum::<u64>(),
+                    expected
+                );
+            }
+        }
+    }
 
-    let mut data = String::new();
-    reader.read_to_string(&mut data)?;
+    #[test]
+    pub fn test_errors() {
+        assert!(align(&crabs(&[]), &Linear).is_err());
+        assert!(align(&crabs(&[0, 5]), &CostTable::parse("0,1,2").unwrap()).is_err());
+        assert!(CostTable::parse("0,1,x").is_err());
+        assert!(CostTable::parse("").is_err());
+        assert!(get_cost("cubic").is_err());
 
-    Ok(data
-        .split(",")
-        .map(|elem| i64::from_str(elem))
-        .collect::<Result<Vec<i64>, _>>()?)
+        // The rightmost crab is a valid target too.
+        let alignment = align(&crabs(&[0, 4, 4]), &CostTable::parse("0 9 9 9 1").unwrap()).unwrap();
+        assert_eq!(alignment.optimal, vec![(4, 4)]);
+        assert_eq!(alignment.cost, 1);
+    }
 }
@@ -1,12 +1,11 @@
-use std::{
-    collections::HashMap,
-    fs::File,
-    io::{BufRead, BufReader},
-};
+use std::collections::HashMap;
 
 use anyhow::Result;
 use itertools::Itertools;
 
+#[path = "../manifest.rs"]
+mod manifest;
+
 fn main() -> Result<()> {
     let input = get_input()?;
     println!("Part 1 result: {}", part1(&input));
@@ -64,32 +63,27 @@ fn part2(input: &Vec<NoteEntry>) -> i32 {
 }
 
 fn get_input() -> Result<Vec<NoteEntry>> {
-    const PATH: &str = "src/day08/input.txt";
-
-    let file = File::open(PATH)?;
-    let reader = BufReader::new(file);
-
-    reader
-        .lines()
-        .map(|line| {
-            let line = line?;
-            let mut parts = line.split(" | ");
-            let patterns = parts
-                .next()
-                .unwrap()
-                .split(" ")
-                .map(|s| s.to_string())
-                .collect::<Vec<String>>();
-            let output = parts
-                .next()
-                .unwrap()
-                .split(" ")
-                .map(|s| s.to_string())
-                .collect::<Vec<String>>();
-
-            Ok(NoteEntry { patterns, output })
-        })
-        .collect::<Result<Vec<NoteEntry>, _>>()
+    manifest::open("day08", None, |data| {
+        data.lines()
+            .map(|line| {
+                let mut parts = line.split(" | ");
+                let patterns = parts
+                    .next()
+                    .unwrap()
+                    .split(" ")
+                    .map(|s| s.to_string())
+                    .collect::<Vec<String>>();
+                let output = parts
+                    .next()
+                    .unwrap()
+                    .split(" ")
+                    .map(|s| s.to_string())
+                    .collect::<Vec<String>>();
+
+                Ok(NoteEntry { patterns, output })
+            })
+            .collect::<Result<Vec<NoteEntry>, _>>()
+    })
 }
 
 struct NoteEntry {
@@ -1,12 +1,11 @@
-use std::{
-    convert::TryFrom,
-    fs::File,
-    io::{BufRead, BufReader},
-};
+use std::convert::TryFrom;
 
 use anyhow::Result;
 use itertools::Itertools;
 
+#[path = "../manifest.rs"]
+mod manifest;
+
 fn main() -> Result<()> {
     let input = get_input()?;
     println!("Part 1 result: {}", part1(&input));
@@ -15,20 +14,15 @@ fn main() -> Result<()> {
 }
 
 fn get_input() -> Result<Vec<Vec<Tag>>> {
-    const PATH: &str = "src/day10/input.txt";
-
-    let file = File::open(PATH)?;
-    let reader = BufReader::new(file);
-
-    reader
-        .lines()
-        .map(|line| line.unwrap().clone())
-        .map(|line| {
-            line.chars()
-                .map(|c| Tag::try_from(c))
-                .collect::<Result<Vec<Tag>>>()
-        })
-        .collect::<Result<Vec<Vec<Tag>>>>()
+    manifest::open("day10", None, |data| {
+        data.lines()
+            .map(|line| {
+                line.chars()
+                    .map(|c| Tag::try_from(c))
+                    .collect::<Result<Vec<Tag>>>()
+            })
+            .collect::<Result<Vec<Vec<Tag>>>>()
+    })
 }
 
 fn part1(data: &Vec<Vec<Tag>>) -> u32 {
@@ -1,13 +1,11 @@
-use std::{
-    collections::HashSet,
-    fs::File,
-    io::{BufRead, BufReader},
-    str::FromStr,
-};
+use std::{collections::HashSet, str::FromStr};
 
 use anyhow::{Context, Result};
 use itertools::iproduct;
 
+#[path = "../manifest.rs"]
+mod manifest;
+
 fn main() -> Result<()> {
     let input = get_input()?;
     println!("Part 1 result: {}", part1(&input));
@@ -26,24 +24,20 @@ fn part2(input: &OctopusGrid) -> usize {
 }
 
 fn get_input() -> Result<OctopusGrid> {
-    const PATH: &str = "src/day11/input.txt";
-
-    let file = File::open(PATH)?;
-    let reader = BufReader::new(file);
-
-    let data = reader
-        .lines()
-        .map(|line| {
-            line?
-                .chars()
-                .map(|c| {
-                    u32::from_str(&c.to_string()).context("Failed to parse character to number")
-                })
-                .collect::<Result<Vec<u32>, _>>()
-        })
-        .collect::<Result<Vec<Vec<u32>>, _>>()?;
-
-    Ok(OctopusGrid::new(data))
+    manifest::open("day11", None, |data| {
+        let data = data
+            .lines()
+            .map(|line| {
+                line.chars()
+                    .map(|c| {
+                        u32::from_str(&c.to_string()).context("Failed to parse character to number")
+                    })
+                    .collect::<Result<Vec<u32>, _>>()
+            })
+            .collect::<Result<Vec<Vec<u32>>, _>>()?;
+
+        Ok(OctopusGrid::new(data))
+    })
 }
 
 #[derive(Clone)]
@@ -1,12 +1,13 @@
 use std::{
     collections::{HashMap, HashSet},
-    fs::File,
-    io::{BufRead, BufReader},
     str::FromStr,
 };
 
 use itertools::Itertools;
 
+#[path = "../manifest.rs"]
+mod manifest;
+
 fn main() -> anyhow::Result<()> {
     let input = get_input()?;
     println!("Part 1 result: {}", part1(&input));
@@ -32,25 +33,20 @@ fn part2(input: &Graph) -> u32 {
 }
 
 fn get_input() -> anyhow::Result<Graph> {
-    const PATH: &str = "src/day12/input.txt";
-
-    let file = File::open(PATH)?;
-    let reader = BufReader::new(file);
-
-    Ok(Graph::new(
-        reader
-            .lines()
-            .map(|line| {
-                let line = line?;
-                let mut parts = line.split("-");
-
-                Ok((
-                    Identifier::from_str(parts.next().unwrap())?,
-                    Identifier::from_str(parts.next().unwrap())?,
-                ))
-            })
-            .collect::<anyhow::Result<Vec<(Identifier, Identifier)>>>()?,
-    ))
+    manifest::open("day12", None, |data| {
+        Ok(Graph::new(
+            data.lines()
+                .map(|line| {
+                    let mut parts = line.split("-");
+
+                    Ok((
+                        Identifier::from_str(parts.next().unwrap())?,
+                        Identifier::from_str(parts.next().unwrap())?,
+                    ))
+                })
+                .collect::<anyhow::Result<Vec<(Identifier, Identifier)>>>()?,
+        ))
+    })
 }
 
 #[derive(Debug)]
@@ -1,12 +1,10 @@
-use std::{
-    collections::HashSet,
-    fs::File,
-    io::{BufRead, BufReader},
-    str::FromStr,
-};
+use std::{collections::HashSet, str::FromStr};
 
 use itertools::Itertools;
 
+#[path = "../manifest.rs"]
+mod manifest;
+
 fn main() -> anyhow::Result<()> {
     let input = get_input()?;
     println!("Part 1 result: {}", part1(&input));
@@ -78,34 +76,29 @@ fn print_points(points: HashSet<Point>) {
 }
 
 fn get_input() -> anyhow::Result<Input> {
-    const PATH: &str = "src/day13/input.txt";
-
-    let file = File::open(PATH)?;
-    let reader = BufReader::new(file);
-
-    let mut input = Input {
-        points: HashSet::new(),
-        insts: vec![],
-    };
-
-    let mut is_points = true;
+    manifest::open("day13", None, |data| {
+        let mut input = Input {
+            points: HashSet::new(),
+            insts: vec![],
+        };
 
-    for line in reader.lines() {
-        let line = line?;
+        let mut is_points = true;
 
-        if line.is_empty() {
-            is_points = false;
-            continue;
-        }
+        for line in data.lines() {
+            if line.is_empty() {
+                is_points = false;
+                continue;
+            }
 
-        if is_points {
-            input.points.insert(Point::from_str(&line)?);
-        } else {
-            input.insts.push(Instruction::from_str(&line)?);
+            if is_points {
+                input.points.insert(Point::from_str(line)?);
+            } else {
+                input.insts.push(Instruction::from_str(line)?);
+            }
         }
-    }
 
-    Ok(input)
+        Ok(input)
+    })
 }
 
 struct Input {
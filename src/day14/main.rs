@@ -1,13 +1,11 @@
-use std::{
-    collections::HashMap,
-    fs::File,
-    io::{BufRead, BufReader},
-    str::FromStr,
-};
+use std::{collections::HashMap, str::FromStr};
 
 use anyhow::Result;
 use itertools::{Itertools, MinMaxResult};
 
+#[path = "../manifest.rs"]
+mod manifest;
+
 fn main() -> Result<()> {
     let input = get_input()?;
     println!("Part 1 result: {}", part1(&input));
@@ -83,27 +81,25 @@ fn apply_process(
 }
 
 fn get_input() -> Result<Input> {
-    const PATH: &str = "src/day14/input.txt";
-
-    let file = File::open(PATH)?;
-    let reader = BufReader::new(file);
-    let mut lines = reader.lines();
+    manifest::open("day14", None, |data| {
+        let mut lines = data.lines();
 
-    let template = lines.next().unwrap()?;
-    lines.next();
+        let template = lines.next().unwrap().to_string();
+        lines.next();
 
-    let rules = lines
-        .map(|line| InsertionRule::from_str(&line?))
-        .collect::<Result<Vec<InsertionRule>, _>>()?;
+        let rules = lines
+            .map(InsertionRule::from_str)
+            .collect::<Result<Vec<InsertionRule>, _>>()?;
 
-    let rules_map = rules
-        .into_iter()
-        .map(|rule| (rule.pattern.clone(), rule))
-        .collect::<HashMap<String, InsertionRule>>();
+        let rules_map = rules
+            .into_iter()
+            .map(|rule| (rule.pattern.clone(), rule))
+            .collect::<HashMap<String, InsertionRule>>();
 
-    Ok(Input {
-        template,
-        rules: rules_map,
+        Ok(Input {
+            template,
+            rules: rules_map,
+        })
     })
 }
 
@@ -1,13 +1,14 @@
 use std::{
     collections::{HashMap, HashSet},
-    fs::File,
-    io::{BufRead, BufReader},
     str::FromStr,
 };
 
 use anyhow::{Context, Result};
 use itertools::iproduct;
 
+#[path = "../manifest.rs"]
+mod manifest;
+
 fn main() -> Result<()> {
     let input = get_input()?;
     println!("Part 1 result: {}", part1(&input));
@@ -38,24 +39,20 @@ fn part2(input: &RiskMap) -> u32 {
 }
 
 fn get_input() -> Result<RiskMap> {
-    const PATH: &str = "src/day15/input.txt";
-
-    let file = File::open(PATH)?;
-    let reader = BufReader::new(file);
-
-    let data = reader
-        .lines()
-        .map(|line| {
-            line?
-                .chars()
-                .map(|c| {
-                    u32::from_str(&c.to_string()).context("Failed to parse character to number")
-                })
-                .collect::<Result<Vec<u32>, _>>()
-        })
-        .collect::<Result<Vec<Vec<u32>>, _>>()?;
-
-    Ok(RiskMap::new(data))
+    manifest::open("day15", None, |data| {
+        let data = data
+            .lines()
+            .map(|line| {
+                line.chars()
+                    .map(|c| {
+                        u32::from_str(&c.to_string()).context("Failed to parse character to number")
+                    })
+                    .collect::<Result<Vec<u32>, _>>()
+            })
+            .collect::<Result<Vec<Vec<u32>>, _>>()?;
+
+        Ok(RiskMap::new(data))
+    })
 }
 
 #[derive(Clone)]
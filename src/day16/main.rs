@@ -1,12 +1,14 @@
 use std::{
     collections::{HashMap, HashSet},
-    fs::File,
-    io::{BufRead, BufReader, Read},
+    io::{BufRead, BufReader},
     str::FromStr,
 };
 
 use anyhow::{Context, Result};
 
+#[path = "../manifest.rs"]
+mod manifest;
+
 fn main() -> Result<()> {
     let input = get_input()?;
     println!("Part 1 result: {}", part1(&input));
@@ -23,11 +25,7 @@ fn part2(input: &Packet) -> u64 {
 }
 
 fn get_input() -> Result<Packet> {
-    const PATH: &str = "src/day16/input.txt";
-
-    let mut file = File::open(PATH)?;
-    let mut data = String::new();
-    file.read_to_string(&mut data)?;
+    let data = manifest::open("day16", None, |data| Ok(data.to_string()))?;
     let bytes = hex::decode(data)?;
     let mut reader = BitReader::new(&bytes);
 
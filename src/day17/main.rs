@@ -1,8 +1,11 @@
-use std::{fs::File, io::Read, str::FromStr};
+use std::str::FromStr;
 
 use anyhow::{Context, Result};
 use regex::Regex;
 
+#[path = "../manifest.rs"]
+mod manifest;
+
 fn main() -> Result<()> {
     let input = get_input()?;
     println!("Part 1 result: {}", part1(&input));
@@ -49,11 +52,7 @@ fn part2(input: &Target) -> usize {
 }
 
 fn get_input() -> Result<Target> {
-    const PATH: &str = "src/day17/input.txt";
-
-    let mut file = File::open(PATH)?;
-    let mut data = String::new();
-    file.read_to_string(&mut data)?;
+    let data = manifest::open("day17", None, |data| Ok(data.to_string()))?;
 
     let re = Regex::new(r"([-0-9]+)").unwrap();
     let captures = re
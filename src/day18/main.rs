@@ -1,12 +1,10 @@
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    ops::Add,
-    str::FromStr,
-};
+use std::{ops::Add, str::FromStr};
 
 use anyhow::{Context, Result};
 
+#[path = "../manifest.rs"]
+mod manifest;
+
 fn main() -> Result<()> {
     let input = get_input()?;
     println!("Part 1 result: {}", part1(&input));
@@ -38,19 +36,12 @@ fn part2(input: &Vec<SnailfishNumber>) -> u64 {
 }
 
 fn get_input() -> Result<Vec<SnailfishNumber>> {
-    const PATH: &str = "src/day18/input.txt";
-
-    let file = File::open(PATH)?;
-    let reader = BufReader::new(file);
-
-    Ok(reader
-        .lines()
-        .map(|line| {
-            let line = line?;
-
-            SnailfishNumber::from_str(&line)
-        })
-        .collect::<Result<Vec<SnailfishNumber>, _>>()?)
+    manifest::open("day18", None, |data| {
+        Ok(data
+            .lines()
+            .map(SnailfishNumber::from_str)
+            .collect::<Result<Vec<SnailfishNumber>, _>>()?)
+    })
 }
 
 #[derive(Debug, Clone)]
@@ -0,0 +1,16 @@
+use anyhow::Context;
+
+// Removes `flag` and the value following it from the arguments.
+pub fn take_flag(args: &mut Vec<String>, flag: &str) -> anyhow::Result<Option<String>> {
+    match args.iter().position(|arg| arg == flag) {
+        Some(idx) => {
+            let value = args
+                .get(idx + 1)
+                .with_context(|| format!("Expected a value after {}", flag))?
+                .clone();
+            args.drain(idx..idx + 2);
+            Ok(Some(value))
+        }
+        None => Ok(None),
+    }
+}
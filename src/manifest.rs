@@ -0,0 +1,326 @@
+// Where each day's puzzle input lives and what it should look like, so a day pointed at the wrong file says so
+// rather than making what sense of it it can.
+use std::fs;
+
+use anyhow::Context;
+use regex::Regex;
+
+pub enum Shape {
+    // A single line of comma separated integers, within a range.
+    IntList { min: i64, max: Option<i64> },
+    // One integer per line.
+    IntLines,
+    // Rows of digits in the given radix, all the same width.
+    DigitGrid { radix: u32 },
+    // A single line of hex digits.
+    HexString,
+    // Every non-blank line matches the pattern.
+    Lines(&'static str),
+}
+
+pub struct Entry {
+    pub day: &'static str,
+    pub path: &'static str,
+    pub shape: Shape,
+}
+
+pub const MANIFEST: &[Entry] = &[
+    Entry {
+        day: "day01",
+        path: "src/day01/input.txt",
+        shape: Shape::IntLines,
+    },
+    Entry {
+        day: "day02",
+        path: "src/day02/input.txt",
+        shape: Shape::Lines(r"\s*(forward|down|up|back|left|right)\s+\+?\d+\s*|\s*surface\s*"),
+    },
+    Entry {
+        day: "day03",
+        path: "src/day03/input.txt",
+        shape: Shape::DigitGrid { radix: 2 },
+    },
+    Entry {
+        day: "day04",
+        path: "src/day04/input.txt",
+        shape: Shape::Lines(r"\d+(,\d+)*|\s*\d+(\s+\d+)*"),
+    },
+    Entry {
+        day: "day05",
+        path: "src/day05/input.txt",
+        shape: Shape::Lines(
+            r"\s*[+-]?\d+(\s*,\s*[+-]?\d+){1,2}\s*->\s*[+-]?\d+(\s*,\s*[+-]?\d+){1,2}\s*",
+        ),
+    },
+    Entry {
+        day: "day06",
+        path: "src/day06/input.txt",
+        shape: Shape::Lines(r"[,\s]*\+?0*[0-8](:\+?\d+)?([,\s]+\+?0*[0-8](:\+?\d+)?)*[,\s]*"),
+    },
+    Entry {
+        day: "day07",
+        path: "src/day07/input.txt",
+        shape: Shape::Lines(r"[,\s]*[+-]?\d+(:\+?\d+)?([,\s]+[+-]?\d+(:\+?\d+)?)*[,\s]*"),
+    },
+    Entry {
+        day: "day08",
+        path: "src/day08/input.txt",
+        shape: Shape::Lines(r"[a-g]+( [a-g]+)* \| [a-g]+( [a-g]+)*"),
+    },
+    Entry {
+        day: "day09",
+        path: "src/day09/input.txt",
+        shape: Shape::DigitGrid { radix: 10 },
+    },
+    Entry {
+        day: "day10",
+        path: "src/day10/input.txt",
+        shape: Shape::Lines(r"[()\[\]{}<>]+"),
+    },
+    Entry {
+        day: "day11",
+        path: "src/day11/input.txt",
+        shape: Shape::DigitGrid { radix: 10 },
+    },
+    Entry {
+        day: "day12",
+        path: "src/day12/input.txt",
+        shape: Shape::Lines(r"\w+-\w+"),
+    },
+    Entry {
+        day: "day13",
+        path: "src/day13/input.txt",
+        shape: Shape::Lines(r"\d+,\d+|fold along [xy]=\d+"),
+    },
+    Entry {
+        day: "day14",
+        path: "src/day14/input.txt",
+        shape: Shape::Lines(r"[A-Z]+|[A-Z]{2} -> [A-Z]"),
+    },
+    Entry {
+        day: "day15",
+        path: "src/day15/input.txt",
+        shape: Shape::DigitGrid { radix: 10 },
+    },
+    Entry {
+        day: "day16",
+        path: "src/day16/input.txt",
+        shape: Shape::HexString,
+    },
+    Entry {
+        day: "day17",
+        path: "src/day17/input.txt",
+        shape: Shape::Lines(r"target area: x=-?\d+\.\.-?\d+, y=-?\d+\.\.-?\d+"),
+    },
+    Entry {
+        day: "day18",
+        path: "src/day18/input.txt",
+        shape: Shape::Lines(r"[\[\],0-9]+"),
+    },
+];
+
+impl Shape {
+    pub fn describe(&self) -> String {
+        match self {
+            Shape::IntList {
+                min,
+                max: Some(max),
+            } => {
+                format!("comma-separated integers from {} to {}", min, max)
+            }
+            Shape::IntList { min, max: None } => {
+                format!("comma-separated integers of at least {}", min)
+            }
+            Shape::IntLines => "one integer per line".to_string(),
+            Shape::DigitGrid { radix: 2 } => "a grid of binary digits".to_string(),
+            Shape::DigitGrid { radix } => format!("a grid of base {} digits", radix),
+            Shape::HexString => "a hex string".to_string(),
+            Shape::Lines(pattern) => format!("lines matching '{}'", pattern),
+        }
+    }
+
+    // The first thing wrong with the data, if anything.
+    pub fn check(&self, data: &str) -> Result<(), String> {
+        let lines = data
+            .lines()
+            .map(str::trim_end)
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| (i + 1, line))
+            .collect::<Vec<(usize, &str)>>();
+
+        if lines.is_empty() {
+            return Err("it's empty".to_string());
+        }
+
+        match self {
+            Shape::IntList { min, max } => {
+                if lines.len() > 1 {
+                    return Err(format!("it spans {} lines", lines.len()));
+                }
+
+                for (i, entry) in lines[0].1.split(',').enumerate() {
+                    let value = entry.trim().parse::<i64>().map_err(|_| {
+                        format!(
+                            "'{}' at position {} isn't an integer",
+                            excerpt(entry),
+                            i + 1
+                        )
+                    })?;
+                    if value < *min || max.is_some_and(|max| value > max) {
+                        return Err(format!("{} at position {} is out of range", value, i + 1));
+                    }
+                }
+            }
+            Shape::IntLines => {
+                for (line_no, line) in lines {
+                    if line.trim().parse::<i64>().is_err() {
+                        return Err(format!(
+                            "line {} ('{}') isn't an integer",
+                            line_no,
+                            excerpt(line)
+                        ));
+                    }
+                }
+            }
+            Shape::DigitGrid { radix } => {
+                let width = lines[0].1.chars().count();
+                for (line_no, line) in lines {
+                    if let Some(c) = line.chars().find(|c| c.to_digit(*radix).is_none()) {
+                        return Err(format!("line {} has '{}', which isn't a digit", line_no, c));
+                    }
+                    if line.chars().count() != width {
+                        return Err(format!(
+                            "line {} is {} wide where the first is {}",
+                            line_no,
+                            line.chars().count(),
+                            width
+                        ));
+                    }
+                }
+            }
+            Shape::HexString => {
+                if lines.len() > 1 {
+                    return Err(format!("it spans {} lines", lines.len()));
+                }
+                if let Some(c) = lines[0].1.trim().chars().find(|c| !c.is_ascii_hexdigit()) {
+                    return Err(format!("it has '{}', which isn't a hex digit", c));
+                }
+            }
+            Shape::Lines(pattern) => {
+                let re = Regex::new(&format!("^(?:{})$", pattern)).unwrap();
+                for (line_no, line) in lines {
+                    if !re.is_match(line) {
+                        return Err(format!(
+                            "line {} ('{}') doesn't match",
+                            line_no,
+                            excerpt(line)
+                        ));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// A rough guess at what the data is, to help spot which day it belongs to.
+pub fn sniff(data: &str) -> String {
+    let candidates = [
+        Shape::DigitGrid { radix: 2 },
+        Shape::DigitGrid { radix: 10 },
+        Shape::IntLines,
+        Shape::IntList {
+            min: i64::MIN,
+            max: None,
+        },
+        Shape::HexString,
+    ];
+
+    if data.trim().is_empty() {
+        return "an empty file".to_string();
+    }
+
+    match candidates.iter().find(|shape| shape.check(data).is_ok()) {
+        Some(Shape::IntList { .. }) => "comma-separated integers".to_string(),
+        Some(shape) => shape.describe(),
+        None => format!(
+            "text starting '{}'",
+            excerpt(data.lines().next().unwrap_or(""))
+        ),
+    }
+}
+
+pub fn entry(day: &str) -> anyhow::Result<&'static Entry> {
+    MANIFEST
+        .iter()
+        .find(|entry| entry.day == day)
+        .ok_or_else(|| anyhow::anyhow!("No input declared for {}", day))
+}
+
+// Whether two paths name the same file, however they're written.
+fn same_file(a: &str, b: &str) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+// Reads a day's input, or another file standing in for it, checks it has the declared shape and parses it with
+// the day's own parser. Another day's input is refused outright, as its shape can pass for this day's. Otherwise
+// either check failing says what the day expected and what the file looks like instead.
+pub fn open<T>(
+    day: &str,
+    path: Option<&str>,
+    parse: impl FnOnce(&str) -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    let entry = entry(day)?;
+    let path = path.unwrap_or(entry.path);
+
+    if let Some(other) = MANIFEST
+        .iter()
+        .find(|other| other.day != day && same_file(path, other.path))
+    {
+        return Err(anyhow::anyhow!(
+            "{} is the input for {}, not {}",
+            path,
+            other.day,
+            day
+        ));
+    }
+
+    let data = fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {} for {}: {}", path, day, e))?;
+
+    let mismatch = || {
+        format!(
+            "{} doesn't look like the input for {}: expected {}",
+            path,
+            day,
+            entry.shape.describe()
+        )
+    };
+
+    entry.shape.check(&data).map_err(|problem| {
+        anyhow::anyhow!(
+            "{}, but {}. It looks like {}.",
+            mismatch(),
+            problem,
+            sniff(&data)
+        )
+    })?;
+
+    parse(&data).with_context(|| format!("{}; it looks like {}", mismatch(), sniff(&data)))
+}
+
+fn excerpt(text: &str) -> String {
+    const LIMIT: usize = 20;
+
+    let text = text.trim();
+    if text.chars().count() > LIMIT {
+        format!("{}...", text.chars().take(LIMIT).collect::<String>())
+    } else {
+        text.to_string()
+    }
+}
@@ -0,0 +1,19 @@
+// A seeded linear congruential generator, so randomised tests check the same cases every run.
+pub struct Lcg {
+    state: u64,
+}
+
+impl Lcg {
+    pub fn new(seed: u64) -> Self {
+        Lcg { state: seed }
+    }
+
+    // A value from zero up to but not including `bound`, taken from the better mixed high bits.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.state = self
+            .state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.state >> 11) % bound
+    }
+}
@@ -0,0 +1,162 @@
+// The manifest is included by every day's binary, so its tests live here to run once.
+#[path = "../src/manifest.rs"]
+mod manifest;
+
+use std::{fs, path::PathBuf};
+
+use manifest::{open, sniff, Shape, MANIFEST};
+
+fn temp_file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("manifest-{}-{}.txt", std::process::id(), name));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+pub fn test_paths_are_distinct() {
+    for (i, entry) in MANIFEST.iter().enumerate() {
+        assert_eq!(entry.path, format!("src/{}/input.txt", entry.day));
+        assert!(MANIFEST[..i].iter().all(|other| other.day != entry.day));
+    }
+}
+
+#[test]
+pub fn test_inputs_match() {
+    for entry in MANIFEST {
+        let data = fs::read_to_string(entry.path)
+            .unwrap_or_else(|e| panic!("Failed to read {}: {}", entry.path, e));
+        assert_eq!(entry.shape.check(&data), Ok(()), "{}", entry.day);
+    }
+}
+
+#[test]
+pub fn test_shapes() {
+    let fish = Shape::IntList {
+        min: 0,
+        max: Some(8),
+    };
+    assert_eq!(fish.check("3,4,3,1,2\n"), Ok(()));
+    assert_eq!(
+        fish.check("16,1,2,0,4,2,7,1,2,14"),
+        Err("16 at position 1 is out of range".to_string())
+    );
+    assert_eq!(
+        fish.check("3,x"),
+        Err("'x' at position 2 isn't an integer".to_string())
+    );
+    assert_eq!(fish.check("3\n4"), Err("it spans 2 lines".to_string()));
+    assert_eq!(fish.check(" \n"), Err("it's empty".to_string()));
+
+    let grid = Shape::DigitGrid { radix: 10 };
+    assert_eq!(grid.check("219\n398\n"), Ok(()));
+    assert_eq!(
+        grid.check("219\n39\n"),
+        Err("line 2 is 2 wide where the first is 3".to_string())
+    );
+    assert_eq!(
+        Shape::DigitGrid { radix: 2 }.check("0102"),
+        Err("line 1 has '2', which isn't a digit".to_string())
+    );
+
+    assert_eq!(Shape::HexString.check("8A004A801A8002F478\n"), Ok(()));
+    assert_eq!(
+        Shape::HexString.check("8A00G"),
+        Err("it has 'G', which isn't a hex digit".to_string())
+    );
+
+    let pairs = Shape::Lines(r"\d+,\d+|fold along [xy]=\d+");
+    assert_eq!(pairs.check("6,10\n\nfold along y=7\n"), Ok(()));
+    assert_eq!(
+        pairs.check("6,10\nfold along z=7"),
+        Err("line 2 ('fold along z=7') doesn't match".to_string())
+    );
+}
+
+// Days whose parsers take more than one format declare shapes covering all of them.
+#[test]
+pub fn test_day_shapes() {
+    let shape = |day: &str| &manifest::entry(day).unwrap().shape;
+
+    assert_eq!(
+        shape("day02").check("forward 5\n  down  8 \nback 3\nleft 2\nright 1\nsurface\nup +3"),
+        Ok(())
+    );
+    assert!(shape("day02").check("forward five").is_err());
+    assert!(shape("day02").check("3,4,3,1,2").is_err());
+
+    assert_eq!(
+        shape("day05").check("0,9 -> 5,9\n-1, 2,3->4,5,-6\n"),
+        Ok(())
+    );
+    assert!(shape("day05").check("0,9 -> 5").is_err());
+    assert!(shape("day05").check("16,1,2,0,4,2,7,1,2,14").is_err());
+
+    assert_eq!(shape("day06").check("3,4,3,1,2\n"), Ok(()));
+    assert_eq!(shape("day06").check("1:1 2:1\n3:2,4:1\n"), Ok(()));
+    assert_eq!(
+        shape("day06").check("16,1,2,0,4,2,7,1,2,14"),
+        Err("line 1 ('16,1,2,0,4,2,7,1,2,1...') doesn't match".to_string())
+    );
+
+    assert_eq!(shape("day07").check("16,1,2,0,4,2,7,1,2,14\n"), Ok(()));
+    assert_eq!(shape("day07").check("16,1:2 2:3,0\n-4, 7 14"), Ok(()));
+    assert!(shape("day07").check("1,x").is_err());
+    assert!(shape("day07").check("0,9 -> 5,9").is_err());
+}
+
+#[test]
+pub fn test_sniff() {
+    assert_eq!(sniff("3,4,3,1,2"), "comma-separated integers");
+    assert_eq!(sniff("00100\n11110\n"), "a grid of binary digits");
+    assert_eq!(sniff("2199\n3987\n"), "a grid of base 10 digits");
+    assert_eq!(sniff("D2FE28"), "a hex string");
+    assert_eq!(sniff("start-A\nA-end"), "text starting 'start-A'");
+    assert_eq!(sniff(""), "an empty file");
+}
+
+#[test]
+pub fn test_open() {
+    let crabs = temp_file("crabs", "16,1,2,0,4,2,7,1,2,14\n");
+    let path = crabs.to_str();
+
+    let err = open("day09", path, |data| Ok(data.len())).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "{} doesn't look like the input for day09: expected a grid of base 10 digits, but line 1 has ',', \
+             which isn't a digit. It looks like comma-separated integers.",
+            crabs.display()
+        )
+    );
+
+    // The parser checks what the shape can't, and says what was expected when it fails.
+    assert_eq!(open("day07", path, |data| Ok(data.len())).unwrap(), 22);
+    let err = open("day07", path, |_| -> anyhow::Result<()> {
+        Err(anyhow::anyhow!("Too many crabs"))
+    })
+    .unwrap_err();
+    assert_eq!(
+        format!("{:#}", err),
+        format!(
+            "{} doesn't look like the input for day07: expected {}; it looks like comma-separated integers: \
+             Too many crabs",
+            crabs.display(),
+            manifest::entry("day07").unwrap().shape.describe()
+        )
+    );
+    assert!(open("day06", path, |_| Ok(())).is_err());
+
+    // Lanternfish timers would pass for crab positions, so another day's input is refused by its path.
+    assert_eq!(
+        open("day07", Some("src/day06/input.txt"), |_| Ok(()))
+            .unwrap_err()
+            .to_string(),
+        "src/day06/input.txt is the input for day06, not day07"
+    );
+    assert!(open("day07", Some("./src/../src/day06/input.txt"), |_| Ok(())).is_err());
+    assert!(open("day07", Some("src/day07/input.txt"), |_| Ok(())).is_ok());
+
+    fs::remove_file(&crabs).unwrap();
+    assert!(open("day06", path, |_| Ok(())).is_err());
+    assert!(open("day19", None, |_| Ok(())).is_err());
+}